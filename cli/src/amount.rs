@@ -0,0 +1,84 @@
+use anyhow::{bail, Result};
+
+// Decimals of lamports, used for native streams
+pub const NATIVE_DECIMALS: u8 = 9;
+
+/// Parses a decimal amount such as `1.5` into base units of a mint with `decimals`.
+pub fn parse_amount(value: &str, decimals: u8) -> Result<u64> {
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    if !digits(whole) || !(fraction.is_empty() || digits(fraction)) {
+        bail!("invalid amount {value:?}");
+    }
+    if fraction.len() > decimals as usize {
+        bail!("amount {value} has more than {decimals} decimals");
+    }
+
+    let fraction = format!("{fraction:0<width$}", width = decimals as usize);
+    let fraction = match fraction.trim_start_matches('0') {
+        "" => Some(0),
+        digits => digits.parse::<u128>().ok(),
+    };
+    // A whole token of a mint with more than 38 decimals does not even fit a u128
+    let units = whole
+        .parse::<u128>()
+        .ok()
+        .and_then(|whole| match whole {
+            0 => Some(0),
+            _ => whole.checked_mul(10u128.checked_pow(decimals as u32)?),
+        })
+        .and_then(|whole| whole.checked_add(fraction?))
+        .and_then(|units| u64::try_from(units).ok());
+
+    units.ok_or_else(|| anyhow::anyhow!("amount {value} is too large"))
+}
+
+/// Renders `units` base units of a mint with `decimals`, without trailing zeros.
+pub fn format_amount(units: u64, decimals: u8) -> String {
+    // Past 19 decimals every u64 is a fraction of a token, and past 38 so is every u128
+    let units = units as u128;
+    let scale = 10u128.checked_pow(decimals as u32).unwrap_or(u128::MAX);
+    let fraction = format!("{:0width$}", units % scale, width = decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+
+    match fraction.is_empty() {
+        true => format!("{}", units / scale),
+        false => format!("{}.{}", units / scale, fraction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats() {
+        assert_eq!(parse_amount("1.5", 9).unwrap(), 1_500_000_000);
+        assert_eq!(parse_amount("0.000001", 6).unwrap(), 1);
+        assert_eq!(parse_amount("42", 0).unwrap(), 42);
+        assert_eq!(format_amount(1_500_000_000, 9), "1.5");
+        assert_eq!(format_amount(1, 6), "0.000001");
+        assert_eq!(format_amount(42, 0), "42");
+    }
+
+    #[test]
+    fn handles_any_decimals() {
+        let smallest = format!("0.{}1", "0".repeat(39));
+        assert_eq!(parse_amount(&smallest, 40).unwrap(), 1);
+        assert_eq!(parse_amount("0", 255).unwrap(), 0);
+        assert_eq!(format_amount(1, 40), smallest);
+        assert_eq!(format_amount(u64::MAX, 20), "0.18446744073709551615");
+        assert_eq!(format_amount(0, 255), "0");
+    }
+
+    #[test]
+    fn rejects_bad_amounts() {
+        assert!(parse_amount("1.0000001", 6).is_err());
+        assert!(parse_amount("1,5", 6).is_err());
+        assert!(parse_amount(".5", 6).is_err());
+        assert!(parse_amount("18446744073709551616", 0).is_err());
+        assert!(parse_amount("1", 39).is_err());
+        assert!(parse_amount(&format!("0.{}", "1".repeat(40)), 40).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Result};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_sdk::account::Account;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use stream_client::errors;
+
+/// What a session reads from and sends to the cluster.
+///
+/// The binary talks to an RPC endpoint, the tests run the same commands against a bank.
+pub trait Cluster {
+    /// The account at `address`, None when it does not exist.
+    fn account(&self, address: &Pubkey) -> Result<Option<Account>>;
+
+    /// Accounts owned by `program` that pass every one of `filters`.
+    fn program_accounts(
+        &self,
+        program: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>>;
+
+    fn latest_blockhash(&self) -> Result<Hash>;
+
+    /// Sends a signed transaction and waits until it is confirmed.
+    fn send(&self, tx: &Transaction) -> Result<Signature>;
+}
+
+impl Cluster for RpcClient {
+    fn account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        Ok(self
+            .get_account_with_commitment(address, self.commitment())?
+            .value)
+    }
+
+    fn program_accounts(
+        &self,
+        program: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        Ok(self.get_program_accounts_with_config(program, config)?)
+    }
+
+    fn latest_blockhash(&self) -> Result<Hash> {
+        Ok(self.get_latest_blockhash()?)
+    }
+
+    fn send(&self, tx: &Transaction) -> Result<Signature> {
+        self.send_and_confirm_transaction(tx).map_err(|err| {
+            match err.get_transaction_error().and_then(|e| program_error(&e)) {
+                Some(named) => named,
+                None => err.into(),
+            }
+        })
+    }
+}
+
+// Names the program error a transaction failed with, when there is one
+pub fn program_error(err: &TransactionError) -> Option<anyhow::Error> {
+    errors::from_transaction_error(err).map(|e| anyhow!("{} ({}): {e}", e.name(), u32::from(e)))
+}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use clap::{Args, ValueEnum};
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use stream_client::{
+    instructions, interval_duration, mul_div_round, pda, CreateStreamParams, StreamAccount,
+};
+
+use crate::amount::{format_amount, parse_amount};
+use crate::session::Session;
+
+/// Who may change the state of a stream, in the order the program numbers them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Party {
+    Sender,
+    Recipient,
+    Both,
+    Neither,
+}
+
+#[derive(Args, Debug)]
+pub struct CreateArgs {
+    /// Identifier of the stream, unique among the streams of the sender
+    #[arg(long)]
+    pub id: String,
+    /// Wallet the stream pays
+    #[arg(long)]
+    pub recipient: Pubkey,
+    /// Amount deposited, in units of the mint
+    #[arg(long)]
+    pub amount: String,
+    /// Seconds over which the amount after the cliff is paid
+    #[arg(long)]
+    pub duration: u64,
+    /// Seconds between two unlocks
+    #[arg(long, default_value_t = 1)]
+    pub interval: u64,
+    /// Token mint of the stream, native SOL when omitted
+    #[arg(long)]
+    pub mint: Option<Pubkey>,
+    #[arg(long, default_value = "")]
+    pub title: String,
+    /// Unix time the stream starts at, now when omitted
+    #[arg(long)]
+    pub start: Option<u64>,
+    /// Amount unlocked at the cliff, in units of the mint
+    #[arg(long, default_value = "0")]
+    pub cliff: String,
+    /// Unix time of the cliff, the start when omitted
+    #[arg(long, default_value_t = 0)]
+    pub cliff_time: u64,
+    /// Keep paying for as long as the stream is reloaded
+    #[arg(long)]
+    pub infinite: bool,
+    /// Vest every second instead of every interval
+    #[arg(long)]
+    pub linear: bool,
+    #[arg(long, value_enum, default_value_t = Party::Sender)]
+    pub cancel_by: Party,
+    #[arg(long, value_enum, default_value_t = Party::Sender)]
+    pub pause_by: Party,
+    #[arg(long, value_enum, default_value_t = Party::Sender)]
+    pub resume_by: Party,
+    #[arg(long, value_enum, default_value_t = Party::Recipient)]
+    pub withdraw_by: Party,
+    #[arg(long, value_enum, default_value_t = Party::Sender)]
+    pub edit_by: Party,
+    /// Integrator taking a fee on every payout
+    #[arg(long)]
+    pub partner: Option<Pubkey>,
+    #[arg(long, default_value_t = 0)]
+    pub partner_fee_bps: u64,
+}
+
+pub fn create(session: &Session, args: CreateArgs) -> Result<Value> {
+    let (asset, decimals) = session.mint(args.mint.as_ref())?;
+    let amount = parse_amount(&args.amount, decimals)?;
+    let cliff_amount = parse_amount(&args.cliff, decimals)?;
+
+    // The rate that pays the amount over the duration, the program then wants the duration
+    // to end with the last interval
+    let streamed = amount
+        .checked_sub(cliff_amount)
+        .ok_or_else(|| anyhow!("the cliff exceeds the amount"))?;
+    // A linear stream stops at exactly the requested duration, an interval one when its
+    // last interval vests
+    let (rate, duration) = mul_div_round(streamed, args.interval, args.duration)
+        .filter(|&rate| rate > 0)
+        .and_then(|rate| {
+            let duration = match args.linear {
+                true => args.duration,
+                false => interval_duration(streamed, args.interval, rate)?,
+            };
+            Some((rate, duration))
+        })
+        .ok_or_else(|| anyhow!("the duration is zero or too long for the amount"))?;
+
+    let params = CreateStreamParams {
+        stream_id: args.id,
+        stream_title: args.title,
+        amount,
+        cliff_amount,
+        is_cliff_percent: false,
+        start: args.start.unwrap_or(0),
+        cliff_time: args.cliff_time,
+        interval: args.interval,
+        rate,
+        duration,
+        is_infinite: args.infinite,
+        cancel_by: args.cancel_by as u8,
+        pause_by: args.pause_by as u8,
+        resume_by: args.resume_by as u8,
+        withdraw_by: args.withdraw_by as u8,
+        edit_by: args.edit_by as u8,
+        start_now: args.start.is_none(),
+        partner: args.partner,
+        partner_fee_bps: args.partner_fee_bps,
+        is_linear: args.linear,
+    };
+
+    let sender = session.pubkey();
+    let signature = session.send(&[instructions::create_stream(
+        &sender,
+        &args.recipient,
+        &asset,
+        &params,
+    )])?;
+
+    Ok(json!({
+        "signature": signature.to_string(),
+        "stream": pda::stream_address(&params.stream_id, &sender).to_string(),
+    }))
+}
+
+pub fn show(session: &Session, address: &Pubkey) -> Result<Value> {
+    let stream = session.stream(address)?;
+    let status = stream
+        .account
+        .status_at(session.now()?)
+        .map_err(|e| anyhow!("cannot compute the status: {e}"))?;
+    let amount = |units: u64| format_amount(units, stream.decimals);
+    let account = &stream.account;
+
+    Ok(json!({
+        "stream": address.to_string(),
+        "id": account.stream_id,
+        "title": account.stream_title,
+        "sender": account.sender.to_string(),
+        "recipient": account.recipient.to_string(),
+        "mint": mint_name(account),
+        "decimals": stream.decimals,
+        "schedule": format!("{:?}", account.schedule),
+        "phase": format!("{:?}", status.phase),
+        "start_time": account.start_time,
+        "stop_time": account.stop_time,
+        "cliff_time": account.cliff_time,
+        "cliff_amount": amount(account.cliff_amount),
+        "interval": account.interval,
+        "rate": amount(account.rate_of_stream),
+        "deposit": amount(account.deposit),
+        "withdrawn": amount(account.withdrawn),
+        "remaining_balance": amount(account.remaining_balance),
+        "withdrawable": amount(status.withdrawable),
+        "vested": amount(status.vested),
+        "unvested": amount(status.unvested),
+        "refundable": amount(status.refundable),
+        "partner": partner_name(&account.partner),
+        "partner_fee_bps": account.partner_fee_bps,
+        "is_infinite": account.is_infinite,
+        "is_split": account.is_split,
+        "timestamp": status.timestamp,
+    }))
+}
+
+/// Streams sent by `sender` and streams paying `recipient`, directly or through a split.
+pub fn list(session: &Session, sender: Option<Pubkey>, recipient: Option<Pubkey>) -> Result<Value> {
+    if sender.is_none() && recipient.is_none() {
+        bail!("pass --sender, --recipient or both");
+    }
+
+    let mut streams = Vec::new();
+    if let Some(sender) = sender {
+        streams.extend(session.streams_of_sender(&sender)?);
+    }
+    if let Some(recipient) = recipient {
+        streams.extend(session.streams_of_recipient(&recipient)?);
+
+        // A split stream pays its split account, which lists the recipient
+        for (split, _) in session
+            .splits()?
+            .into_iter()
+            .filter(|(_, split)| split.position(&recipient).is_some())
+        {
+            streams.extend(session.streams_of_recipient(&split)?);
+        }
+    }
+    // A stream between the sender and the recipient is found twice
+    streams.sort_by_key(|(address, _)| *address);
+    streams.dedup_by_key(|(address, _)| *address);
+
+    let now = session.now()?;
+    let mut decimals: HashMap<Pubkey, u8> = HashMap::new();
+    let mut items = Vec::new();
+
+    for (address, stream) in streams {
+        let decimals = match decimals.get(&stream.token_address) {
+            Some(decimals) => *decimals,
+            None => {
+                let (_, d) = session.asset(&stream)?;
+                decimals.insert(stream.token_address, d);
+                d
+            }
+        };
+        items.push(summary(&address, &stream, decimals, now)?);
+    }
+
+    Ok(Value::Array(items))
+}
+
+/// Withdraws for the payer: its share of a split stream, or the recipient's payout,
+/// optionally to another destination.
+pub fn withdraw(
+    session: &Session,
+    address: &Pubkey,
+    amount: Option<String>,
+    to: Option<Pubkey>,
+) -> Result<Value> {
+    let stream = session.stream(address)?;
+    let treasury = session.treasury()?;
+    let amount = amount
+        .map(|amount| parse_amount(&amount, stream.decimals))
+        .transpose()?;
+
+    let ix = match (stream.account.is_split, to) {
+        (true, None) if amount.is_none() => {
+            instructions::withdraw_share(&stream.keys, &session.pubkey(), treasury.as_ref())
+        }
+        (true, _) => bail!("split streams pay out whole shares to their recipients"),
+        (false, Some(to)) => {
+            session.require_signer(&stream.account.recipient, "recipient")?;
+            instructions::withdraw_to(&stream.keys, &to, treasury.as_ref(), amount)
+        }
+        (false, None) => {
+            instructions::withdraw(&stream.keys, &session.pubkey(), treasury.as_ref(), amount)
+        }
+    };
+    let signature = session.send(&[ix])?;
+
+    let after = session.stream(address)?;
+    Ok(json!({
+        "signature": signature.to_string(),
+        "stream": address.to_string(),
+        "withdrawn": format_amount(
+            after.account.withdrawn.saturating_sub(stream.account.withdrawn),
+            stream.decimals
+        ),
+    }))
+}
+
+pub fn pause(session: &Session, address: &Pubkey) -> Result<Value> {
+    let stream = session.stream(address)?;
+    let ix = instructions::pause(
+        &stream.keys,
+        &session.pubkey(),
+        session.treasury()?.as_ref(),
+    );
+    transaction(session, address, ix)
+}
+
+pub fn resume(session: &Session, address: &Pubkey) -> Result<Value> {
+    let stream = session.stream(address)?;
+    let ix = instructions::resume(&stream.keys, &session.pubkey());
+    transaction(session, address, ix)
+}
+
+pub fn reload(session: &Session, address: &Pubkey, amount: &str) -> Result<Value> {
+    let stream = session.stream(address)?;
+    session.require_signer(&stream.account.sender, "sender")?;
+    let ix = instructions::reload(&stream.keys, parse_amount(amount, stream.decimals)?);
+    transaction(session, address, ix)
+}
+
+pub fn cancel(session: &Session, address: &Pubkey) -> Result<Value> {
+    let stream = session.stream(address)?;
+    let ix = instructions::cancel(
+        &stream.keys,
+        &session.pubkey(),
+        session.treasury()?.as_ref(),
+    );
+    transaction(session, address, ix)
+}
+
+pub fn delete(session: &Session, address: &Pubkey) -> Result<Value> {
+    let stream = session.stream(address)?;
+    session.require_signer(&stream.account.sender, "sender")?;
+    transaction(session, address, instructions::delete(&stream.keys))
+}
+
+fn transaction(
+    session: &Session,
+    address: &Pubkey,
+    ix: solana_sdk::instruction::Instruction,
+) -> Result<Value> {
+    let signature = session.send(&[ix])?;
+    Ok(json!({
+        "signature": signature.to_string(),
+        "stream": address.to_string(),
+    }))
+}
+
+fn summary(address: &Pubkey, stream: &StreamAccount, decimals: u8, now: u64) -> Result<Value> {
+    let status = stream
+        .status_at(now)
+        .map_err(|e| anyhow!("cannot compute the status of {address}: {e}"))?;
+    let amount = |units: u64| format_amount(units, decimals);
+
+    Ok(json!({
+        "stream": address.to_string(),
+        "id": stream.stream_id,
+        "title": stream.stream_title,
+        "sender": stream.sender.to_string(),
+        "recipient": stream.recipient.to_string(),
+        "mint": mint_name(stream),
+        "phase": format!("{:?}", status.phase),
+        "deposit": amount(stream.deposit),
+        "withdrawn": amount(stream.withdrawn),
+        "withdrawable": amount(status.withdrawable),
+        "remaining_balance": amount(stream.remaining_balance),
+    }))
+}
+
+fn mint_name(stream: &StreamAccount) -> String {
+    match stream.is_native() {
+        true => "native".to_string(),
+        false => stream.token_address.to_string(),
+    }
+}
+
+fn partner_name(partner: &Pubkey) -> Value {
+    match *partner == Pubkey::default() {
+        true => Value::Null,
+        false => Value::String(partner.to_string()),
+    }
+}
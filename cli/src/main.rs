@@ -0,0 +1,113 @@
+//! `hadespay`, operates stream_contract streams against any RPC endpoint.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+
+mod amount;
+mod cluster;
+mod commands;
+mod render;
+mod session;
+
+use commands::CreateArgs;
+use render::Format;
+use session::Session;
+
+#[derive(Parser, Debug)]
+#[command(name = "hadespay", version, about = "Create and operate token streams")]
+struct Cli {
+    /// RPC endpoint, or one of localhost, devnet, testnet and mainnet-beta
+    #[arg(short, long, global = true, default_value = "localhost")]
+    url: String,
+    /// Keypair that signs and pays, ~/.config/solana/id.json when omitted
+    #[arg(short, long, global = true)]
+    keypair: Option<PathBuf>,
+    #[arg(short, long, global = true, value_enum, default_value_t = Format::Human)]
+    output: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create a stream from the keypair to a recipient
+    Create(CreateArgs),
+    /// Show a stream and what it owes right now
+    Show { stream: Pubkey },
+    /// List the streams of a sender or a recipient
+    List {
+        #[arg(long)]
+        sender: Option<Pubkey>,
+        #[arg(long)]
+        recipient: Option<Pubkey>,
+    },
+    /// Withdraw what a stream owes the keypair
+    Withdraw {
+        stream: Pubkey,
+        /// Amount to withdraw, everything withdrawable when omitted
+        #[arg(long)]
+        amount: Option<String>,
+        /// Wallet, or token account for token streams, to pay instead of the recipient
+        #[arg(long)]
+        to: Option<Pubkey>,
+    },
+    /// Pause a stream, paying out what it owes first
+    Pause { stream: Pubkey },
+    /// Resume a paused stream
+    Resume { stream: Pubkey },
+    /// Deposit more into a stream of the keypair
+    Reload {
+        stream: Pubkey,
+        #[arg(long)]
+        amount: String,
+    },
+    /// Cancel a stream, paying the recipient and refunding the sender
+    Cancel { stream: Pubkey },
+    /// Close an emptied stream of the keypair
+    Delete { stream: Pubkey },
+}
+
+fn run(cli: Cli) -> Result<Value> {
+    let session = Session::new(&cli.url, cli.keypair)?;
+    execute(&session, cli.command)
+}
+
+fn execute(session: &Session, command: Command) -> Result<Value> {
+    match command {
+        Command::Create(args) => commands::create(session, args),
+        Command::Show { stream } => commands::show(session, &stream),
+        Command::List { sender, recipient } => commands::list(session, sender, recipient),
+        Command::Withdraw { stream, amount, to } => {
+            commands::withdraw(session, &stream, amount, to)
+        }
+        Command::Pause { stream } => commands::pause(session, &stream),
+        Command::Resume { stream } => commands::resume(session, &stream),
+        Command::Reload { stream, amount } => commands::reload(session, &stream, &amount),
+        Command::Cancel { stream } => commands::cancel(session, &stream),
+        Command::Delete { stream } => commands::delete(session, &stream),
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let format = cli.output;
+
+    match run(cli) {
+        Ok(value) => {
+            render::print(format, &value);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
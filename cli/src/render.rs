@@ -0,0 +1,48 @@
+use clap::ValueEnum;
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Human,
+    Json,
+}
+
+/// Prints the result of a command, JSON as is or one `key  value` line per field.
+pub fn print(format: Format, value: &Value) {
+    match format {
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(value).expect("JSON values always serialize")
+        ),
+        Format::Human => print_human(value),
+    }
+}
+
+fn print_human(value: &Value) {
+    match value {
+        Value::Array(items) if items.is_empty() => println!("nothing found"),
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                print_human(item);
+            }
+        }
+        Value::Object(fields) => {
+            let width = fields.keys().map(|k| k.len()).max().unwrap_or(0);
+            for (key, value) in fields {
+                println!("{key:width$}  {}", scalar(value));
+            }
+        }
+        other => println!("{}", scalar(other)),
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "-".to_string(),
+        other => other.to_string(),
+    }
+}
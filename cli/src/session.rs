@@ -0,0 +1,203 @@
+use std::path::PathBuf;
+
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anchor_spl::token_interface::Mint;
+use anyhow::{anyhow, bail, Context, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::clock::Clock;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+use solana_sdk::sysvar;
+use solana_sdk::transaction::Transaction;
+use stream_client::{accounts, pda, Asset, SplitAccount, StreamAccount, StreamRef, ID};
+
+use crate::amount::NATIVE_DECIMALS;
+use crate::cluster::Cluster;
+
+/// A stream fetched from the cluster, with what is needed to build its instructions.
+pub struct Stream {
+    pub keys: StreamRef,
+    pub account: StreamAccount,
+    pub decimals: u8,
+}
+
+/// Connection to the cluster and the keypair signing and paying for every transaction.
+pub struct Session {
+    pub cluster: Box<dyn Cluster>,
+    pub payer: Keypair,
+}
+
+impl Session {
+    pub fn new(url: &str, keypair: Option<PathBuf>) -> Result<Self> {
+        let url = match url {
+            "localhost" => "http://127.0.0.1:8899",
+            "devnet" => "https://api.devnet.solana.com",
+            "testnet" => "https://api.testnet.solana.com",
+            "mainnet-beta" => "https://api.mainnet-beta.solana.com",
+            url => url,
+        };
+        let path = match keypair {
+            Some(path) => path,
+            None => PathBuf::from(std::env::var("HOME").context("HOME is not set")?)
+                .join(".config/solana/id.json"),
+        };
+        let payer = read_keypair_file(&path)
+            .map_err(|e| anyhow!("cannot read keypair {}: {e}", path.display()))?;
+
+        Ok(Session {
+            cluster: Box::new(RpcClient::new_with_commitment(
+                url.to_string(),
+                CommitmentConfig::confirmed(),
+            )),
+            payer,
+        })
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    /// Sends `instructions` in one transaction signed by the payer and waits for it.
+    pub fn send(&self, instructions: &[Instruction]) -> Result<Signature> {
+        let blockhash = self.cluster.latest_blockhash()?;
+        let mut tx = Transaction::new_with_payer(instructions, Some(&self.pubkey()));
+        tx.try_sign(&[&self.payer], blockhash)?;
+
+        self.cluster.send(&tx)
+    }
+
+    pub fn stream(&self, address: &Pubkey) -> Result<Stream> {
+        let data = self
+            .cluster
+            .account(address)?
+            .with_context(|| format!("stream {address} does not exist"))?
+            .data;
+        let account = accounts::decode_stream(&data)
+            .map_err(|e| anyhow!("{address} is not a stream: {e}"))?;
+        let (asset, decimals) = self.asset(&account)?;
+
+        Ok(Stream {
+            keys: StreamRef {
+                asset,
+                ..StreamRef::new(&account, Pubkey::default())
+            },
+            account,
+            decimals,
+        })
+    }
+
+    /// Asset and decimals of a token, native when `mint` is None.
+    pub fn mint(&self, mint: Option<&Pubkey>) -> Result<(Asset, u8)> {
+        let Some(mint) = mint else {
+            return Ok((Asset::Native, NATIVE_DECIMALS));
+        };
+
+        let account = self
+            .cluster
+            .account(mint)?
+            .with_context(|| format!("mint {mint} does not exist"))?;
+        let decimals = Mint::try_deserialize(&mut &account.data[..])
+            .map_err(|e| anyhow!("{mint} is not a mint: {e}"))?
+            .decimals;
+
+        Ok((
+            Asset::Token {
+                mint: *mint,
+                token_program: account.owner,
+            },
+            decimals,
+        ))
+    }
+
+    pub fn asset(&self, stream: &StreamAccount) -> Result<(Asset, u8)> {
+        match stream.is_native() {
+            true => self.mint(None),
+            false => self.mint(Some(&stream.token_address)),
+        }
+    }
+
+    /// Treasury to pass to the payout instructions, none while the protocol charges no fee.
+    pub fn treasury(&self) -> Result<Option<Pubkey>> {
+        let data = self
+            .cluster
+            .account(&pda::config_address())?
+            .context("the program config does not exist")?
+            .data;
+        let config =
+            accounts::decode_config(&data).map_err(|e| anyhow!("invalid program config: {e}"))?;
+        Ok(config.fee_treasury())
+    }
+
+    /// Unix time of the cluster.
+    pub fn now(&self) -> Result<u64> {
+        let account = self
+            .cluster
+            .account(&sysvar::clock::ID)?
+            .context("the clock sysvar does not exist")?;
+        let clock: Clock = solana_sdk::account::from_account(&account)
+            .ok_or_else(|| anyhow!("invalid clock sysvar"))?;
+        Ok(clock.unix_timestamp.max(0) as u64)
+    }
+
+    /// Streams sent by `sender`, from getProgramAccounts.
+    pub fn streams_of_sender(&self, sender: &Pubkey) -> Result<Vec<(Pubkey, StreamAccount)>> {
+        self.streams_with(StreamAccount::SENDER_OFFSET, sender)
+    }
+
+    /// Streams paying `recipient`, a wallet or a split account.
+    pub fn streams_of_recipient(&self, recipient: &Pubkey) -> Result<Vec<(Pubkey, StreamAccount)>> {
+        self.streams_with(StreamAccount::RECIPIENT_OFFSET, recipient)
+    }
+
+    fn streams_with(&self, offset: usize, key: &Pubkey) -> Result<Vec<(Pubkey, StreamAccount)>> {
+        self.program_accounts(
+            &StreamAccount::DISCRIMINATOR,
+            vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                offset,
+                key.to_bytes().to_vec(),
+            ))],
+            accounts::decode_stream,
+        )
+    }
+
+    /// Every split account of the program, their recipients sit in a list that cannot be
+    /// filtered on.
+    pub fn splits(&self) -> Result<Vec<(Pubkey, SplitAccount)>> {
+        self.program_accounts(
+            &SplitAccount::DISCRIMINATOR,
+            Vec::new(),
+            accounts::decode_split,
+        )
+    }
+
+    fn program_accounts<T>(
+        &self,
+        discriminator: &[u8],
+        mut filters: Vec<RpcFilterType>,
+        decode: impl Fn(&[u8]) -> anchor_lang::Result<T>,
+    ) -> Result<Vec<(Pubkey, T)>> {
+        filters.insert(
+            0,
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, discriminator.to_vec())),
+        );
+        let accounts = self.cluster.program_accounts(&ID, filters)?;
+        Ok(accounts
+            .into_iter()
+            .filter_map(|(address, account)| Some((address, decode(&account.data).ok()?)))
+            .collect())
+    }
+
+    /// Fails unless the payer is `expected`, the only signer of the instruction.
+    pub fn require_signer(&self, expected: &Pubkey, role: &str) -> Result<()> {
+        if self.pubkey() != *expected {
+            bail!(
+                "{} is not the {role} of the stream, which is {expected}",
+                self.pubkey()
+            );
+        }
+        Ok(())
+    }
+}
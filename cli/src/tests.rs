@@ -0,0 +1,268 @@
+//! Runs the commands of `hadespay` against a solana-program-test bank with the program
+//! loaded natively, so they need neither a validator nor a deployed build of the program.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use anchor_lang::prelude::AccountInfo;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use serde_json::Value;
+use solana_client::rpc_filter::RpcFilterType;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::{Account, AccountSharedData};
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::clock::Clock;
+use solana_sdk::hash::Hash;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+use stream_client::{instructions, pda, ID};
+use tokio::runtime::Runtime;
+
+use crate::cluster::{program_error, Cluster};
+use crate::session::Session;
+use crate::{execute, Cli};
+
+// Unix time the clock is set to when a test starts
+const T0: u64 = 1_700_000_000;
+
+// The runtime hands the accounts over for the duration of the call only
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    stream_contract::entry(program_id, accounts, data)
+}
+
+/// A bank shared by the sessions of a test.
+///
+/// Banks cannot list the accounts of a program, so every account a transaction touched is
+/// remembered and `program_accounts` filters those.
+#[derive(Clone)]
+struct Bank(Rc<BankState>);
+
+struct BankState {
+    runtime: Runtime,
+    ctx: RefCell<ProgramTestContext>,
+    touched: RefCell<BTreeSet<Pubkey>>,
+}
+
+impl Bank {
+    /// The program with its config initialized by `admin`, and the clock at `T0`.
+    fn start(admin: &Keypair) -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let mut program = ProgramTest::new("stream_contract", ID, processor!(process_instruction));
+        program.prefer_bpf(false);
+        let program_data = UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(admin.pubkey()),
+        };
+        program.add_account(
+            pda::program_data_address(),
+            Account::new_data(LAMPORTS_PER_SOL, &program_data, &bpf_loader_upgradeable::ID)
+                .unwrap(),
+        );
+        let ctx = runtime.block_on(program.start_with_context());
+
+        let bank = Bank(Rc::new(BankState {
+            runtime,
+            ctx: RefCell::new(ctx),
+            touched: RefCell::new(BTreeSet::new()),
+        }));
+        bank.warp(T0);
+        bank.fund(&admin.pubkey());
+        bank.session(admin)
+            .send(&[instructions::initialize_config(&admin.pubkey())])
+            .unwrap();
+        bank
+    }
+
+    fn session(&self, keypair: &Keypair) -> Session {
+        Session {
+            cluster: Box::new(self.clone()),
+            payer: keypair.insecure_clone(),
+        }
+    }
+
+    /// A new keypair holding 10 SOL.
+    fn user(&self) -> Keypair {
+        let user = Keypair::new();
+        self.fund(&user.pubkey());
+        user
+    }
+
+    fn fund(&self, to: &Pubkey) {
+        let tx = {
+            let ctx = self.0.ctx.borrow();
+            Transaction::new_signed_with_payer(
+                &[system_instruction::transfer(
+                    &ctx.payer.pubkey(),
+                    to,
+                    10 * LAMPORTS_PER_SOL,
+                )],
+                Some(&ctx.payer.pubkey()),
+                &[&ctx.payer],
+                ctx.last_blockhash,
+            )
+        };
+        self.send(&tx).unwrap();
+    }
+
+    /// Moves to the next slot with the clock at `timestamp`.
+    fn warp(&self, timestamp: u64) {
+        let mut ctx = self.0.ctx.borrow_mut();
+        let mut clock: Clock = self
+            .0
+            .runtime
+            .block_on(ctx.banks_client.get_sysvar())
+            .unwrap();
+        ctx.warp_to_slot(clock.slot + 1).unwrap();
+        clock.slot += 1;
+        clock.unix_timestamp = timestamp as i64;
+        ctx.set_sysvar(&clock);
+    }
+
+    /// Runs `hadespay` with `args` as `keypair` and returns its JSON output.
+    fn hadespay(&self, keypair: &Keypair, args: &[&str]) -> Result<Value> {
+        let cli = Cli::try_parse_from(["hadespay"].iter().chain(args))?;
+        execute(&self.session(keypair), cli.command)
+    }
+}
+
+impl Cluster for Bank {
+    fn account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        let mut ctx = self.0.ctx.borrow_mut();
+        Ok(self
+            .0
+            .runtime
+            .block_on(ctx.banks_client.get_account(*address))?)
+    }
+
+    fn program_accounts(
+        &self,
+        program: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let touched: Vec<Pubkey> = self.0.touched.borrow().iter().copied().collect();
+        let mut accounts = Vec::new();
+        for address in touched {
+            let Some(account) = self.account(&address)? else {
+                continue;
+            };
+            let shared = AccountSharedData::from(account.clone());
+            if account.owner == *program && filters.iter().all(|f| f.allows(&shared)) {
+                accounts.push((address, account));
+            }
+        }
+        Ok(accounts)
+    }
+
+    fn latest_blockhash(&self) -> Result<Hash> {
+        let mut ctx = self.0.ctx.borrow_mut();
+        Ok(self
+            .0
+            .runtime
+            .block_on(ctx.banks_client.get_latest_blockhash())?)
+    }
+
+    fn send(&self, tx: &Transaction) -> Result<Signature> {
+        self.0
+            .touched
+            .borrow_mut()
+            .extend(tx.message.account_keys.iter().copied());
+
+        let mut ctx = self.0.ctx.borrow_mut();
+        self.0
+            .runtime
+            .block_on(ctx.banks_client.process_transaction(tx.clone()))
+            .map_err(|err| match &err {
+                BanksClientError::TransactionError(e) => {
+                    program_error(e).unwrap_or_else(|| anyhow!(err))
+                }
+                _ => anyhow!(err),
+            })?;
+        Ok(tx.signatures[0])
+    }
+}
+
+#[test]
+fn native_stream_lifecycle() {
+    let bank = Bank::start(&Keypair::new());
+    let sender = bank.user();
+    let recipient = bank.user();
+    let recipient_key = recipient.pubkey().to_string();
+
+    let created = bank
+        .hadespay(
+            &sender,
+            &[
+                "create",
+                "--id",
+                "payroll",
+                "--recipient",
+                &recipient_key,
+                "--amount",
+                "1",
+                "--duration",
+                "100",
+            ],
+        )
+        .unwrap();
+    let stream = created["stream"].as_str().unwrap().to_string();
+
+    // A stream between two other wallets stays out of the listings
+    let other = bank.user();
+    bank.hadespay(
+        &other,
+        &[
+            "create",
+            "--id",
+            "other",
+            "--recipient",
+            &bank.user().pubkey().to_string(),
+            "--amount",
+            "1",
+            "--duration",
+            "100",
+        ],
+    )
+    .unwrap();
+
+    bank.warp(T0 + 25);
+    let shown = bank.hadespay(&recipient, &["show", &stream]).unwrap();
+    assert_eq!(shown["phase"], "Streaming");
+    assert_eq!(shown["withdrawable"], "0.25");
+
+    let withdrawn = bank.hadespay(&recipient, &["withdraw", &stream]).unwrap();
+    assert_eq!(withdrawn["withdrawn"], "0.25");
+
+    for args in [
+        ["list", "--sender", &sender.pubkey().to_string()],
+        ["list", "--recipient", &recipient_key],
+    ] {
+        let listed = bank.hadespay(&recipient, &args).unwrap();
+        assert_eq!(listed.as_array().unwrap().len(), 1, "{args:?}");
+        assert_eq!(listed[0]["stream"], stream.as_str());
+    }
+
+    // Only the sender may pause by default
+    let error = bank.hadespay(&recipient, &["pause", &stream]).unwrap_err();
+    assert!(error.to_string().contains("NotAuthorized"), "{error}");
+
+    bank.warp(T0 + 50);
+    bank.hadespay(&sender, &["cancel", &stream]).unwrap();
+    let cancelled = bank.hadespay(&sender, &["show", &stream]).unwrap();
+    assert_eq!(cancelled["phase"], "Cancelled");
+    assert_eq!(cancelled["withdrawn"], "0.5");
+}
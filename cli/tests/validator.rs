@@ -0,0 +1,343 @@
+//! Runs the `hadespay` binary against a local `solana-test-validator` with the program
+//! deployed from `target/deploy/stream_contract.so`, as built by `anchor build`.
+//!
+//! These are ignored by default, run them with `cargo test -p hadespay -- --ignored`. The
+//! same commands run against a program-test bank in `src/tests.rs` without a validator.
+//! `STREAM_CONTRACT_SO` overrides the path of the program.
+
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anchor_spl::token::spl_token;
+use serde_json::Value;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{write_keypair_file, Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+use spl_associated_token_account::instruction::create_associated_token_account;
+use stream_client::{instructions, pda, ID};
+use tempfile::TempDir;
+
+struct Validator {
+    process: Child,
+    url: String,
+    rpc: RpcClient,
+    dir: TempDir,
+}
+
+impl Validator {
+    /// Starts a fresh validator with the program deployed and its config initialized.
+    fn start() -> Self {
+        let dir = TempDir::new().unwrap();
+        let program = std::env::var_os("STREAM_CONTRACT_SO")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/deploy/stream_contract.so")
+            });
+        assert!(program.exists(), "{} not found", program.display());
+
+        // Deployed as upgradeable, its upgrade authority initializes the config
+        let admin = Keypair::new();
+        let port = free_port();
+        let process = Command::new("solana-test-validator")
+            .arg("--reset")
+            .arg("--quiet")
+            .args(["--ledger", dir.path().join("ledger").to_str().unwrap()])
+            .args(["--rpc-port", &port.to_string()])
+            .args(["--faucet-port", &free_port().to_string()])
+            .args(["--upgradeable-program", &ID.to_string()])
+            .arg(program.to_str().unwrap())
+            .arg(admin.pubkey().to_string())
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("solana-test-validator is not installed");
+
+        let url = format!("http://127.0.0.1:{port}");
+        let rpc = RpcClient::new_with_commitment(url.clone(), CommitmentConfig::confirmed());
+        let started = Instant::now();
+        while rpc.get_latest_blockhash().is_err() {
+            assert!(
+                started.elapsed() < Duration::from_secs(60),
+                "validator did not start"
+            );
+            sleep(Duration::from_millis(250));
+        }
+
+        let validator = Validator {
+            process,
+            url,
+            rpc,
+            dir,
+        };
+        validator.fund(&admin, "admin");
+        validator.send(&admin, &[instructions::initialize_config(&admin.pubkey())]);
+        validator
+    }
+
+    /// A new keypair with 10 SOL, written to a file the CLI can read.
+    fn funded_keypair(&self, name: &str) -> Keypair {
+        let keypair = Keypair::new();
+        self.fund(&keypair, name);
+        keypair
+    }
+
+    /// Airdrops 10 SOL to `keypair` and writes it to a file the CLI can read.
+    fn fund(&self, keypair: &Keypair, name: &str) {
+        write_keypair_file(keypair, self.keypair_path(keypair)).unwrap();
+        let signature = self
+            .rpc
+            .request_airdrop(&keypair.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .unwrap_or_else(|e| panic!("airdrop to {name} failed: {e}"));
+        while !self.rpc.confirm_transaction(&signature).unwrap() {
+            sleep(Duration::from_millis(250));
+        }
+    }
+
+    fn keypair_path(&self, keypair: &Keypair) -> PathBuf {
+        self.dir.path().join(format!("{}.json", keypair.pubkey()))
+    }
+
+    fn send(&self, payer: &Keypair, instructions: &[Instruction]) {
+        self.send_with(payer, &[], instructions);
+    }
+
+    fn send_with(&self, payer: &Keypair, signers: &[&Keypair], instructions: &[Instruction]) {
+        let mut all: Vec<&Keypair> = vec![payer];
+        all.extend(signers);
+        let tx = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &all,
+            self.rpc.get_latest_blockhash().unwrap(),
+        );
+        self.rpc.send_and_confirm_transaction(&tx).unwrap();
+    }
+
+    /// A Token program mint with `decimals`, `supply` of which `owner` holds in its
+    /// associated token account.
+    fn mint(&self, owner: &Keypair, decimals: u8, supply: u64) -> Pubkey {
+        let mint = Keypair::new();
+        let rent = self
+            .rpc
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)
+            .unwrap();
+        let tokens = pda::token_account_address(&owner.pubkey(), &mint.pubkey(), &spl_token::ID);
+
+        self.send_with(
+            owner,
+            &[&mint],
+            &[
+                system_instruction::create_account(
+                    &owner.pubkey(),
+                    &mint.pubkey(),
+                    rent,
+                    spl_token::state::Mint::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_mint2(
+                    &spl_token::ID,
+                    &mint.pubkey(),
+                    &owner.pubkey(),
+                    None,
+                    decimals,
+                )
+                .unwrap(),
+                create_associated_token_account(
+                    &owner.pubkey(),
+                    &owner.pubkey(),
+                    &mint.pubkey(),
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::mint_to(
+                    &spl_token::ID,
+                    &mint.pubkey(),
+                    &tokens,
+                    &owner.pubkey(),
+                    &[],
+                    supply,
+                )
+                .unwrap(),
+            ],
+        );
+        mint.pubkey()
+    }
+
+    fn run(&self, keypair: &Keypair, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_hadespay"))
+            .args(["--url", &self.url, "--output", "json", "--keypair"])
+            .arg(self.keypair_path(keypair))
+            .args(args)
+            .output()
+            .unwrap()
+    }
+
+    /// Runs the CLI as `keypair`, expecting success, and returns its JSON output.
+    fn hadespay(&self, keypair: &Keypair, args: &[&str]) -> Value {
+        let output = self.run(keypair, args);
+        assert!(
+            output.status.success(),
+            "hadespay {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+
+    /// Runs the CLI as `keypair`, expecting failure, and returns its error message.
+    fn hadespay_err(&self, keypair: &Keypair, args: &[&str]) -> String {
+        let output = self.run(keypair, args);
+        assert!(!output.status.success(), "hadespay {args:?} succeeded");
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    }
+}
+
+impl Drop for Validator {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+#[test]
+#[ignore = "needs solana-test-validator and target/deploy/stream_contract.so"]
+fn native_stream_lifecycle() {
+    let validator = Validator::start();
+    let sender = validator.funded_keypair("sender");
+    let recipient = validator.funded_keypair("recipient");
+    let recipient_key = recipient.pubkey().to_string();
+
+    let created = validator.hadespay(
+        &sender,
+        &[
+            "create",
+            "--id",
+            "payroll",
+            "--recipient",
+            &recipient_key,
+            "--amount",
+            "1",
+            "--duration",
+            "100",
+            "--infinite",
+        ],
+    );
+    let stream = created["stream"].as_str().unwrap().to_string();
+
+    let shown = validator.hadespay(&sender, &["show", &stream]);
+    assert_eq!(shown["deposit"], "1");
+    assert_eq!(shown["rate"], "0.01");
+    assert_eq!(shown["mint"], "native");
+    assert_eq!(shown["recipient"], recipient_key.as_str());
+
+    sleep(Duration::from_secs(3));
+    let withdrawn = validator.hadespay(&recipient, &["withdraw", &stream]);
+    assert_ne!(withdrawn["withdrawn"], "0");
+
+    let listed = validator.hadespay(&recipient, &["list", "--recipient", &recipient_key]);
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(listed[0]["stream"], stream.as_str());
+
+    validator.hadespay(&sender, &["pause", &stream]);
+    assert_eq!(
+        validator.hadespay(&sender, &["show", &stream])["phase"],
+        "Paused"
+    );
+    validator.hadespay(&sender, &["resume", &stream]);
+
+    validator.hadespay(&sender, &["reload", &stream, "--amount", "0.5"]);
+    assert_eq!(
+        validator.hadespay(&sender, &["show", &stream])["deposit"],
+        "1.5"
+    );
+
+    validator.hadespay(&sender, &["cancel", &stream]);
+    let cancelled = validator.hadespay(&sender, &["show", &stream]);
+    assert_eq!(cancelled["phase"], "Cancelled");
+    assert_eq!(cancelled["remaining_balance"], "0");
+
+    validator.hadespay(&sender, &["delete", &stream]);
+    validator.hadespay_err(&sender, &["show", &stream]);
+}
+
+#[test]
+#[ignore = "needs solana-test-validator and target/deploy/stream_contract.so"]
+fn token_amounts_use_the_mint_decimals() {
+    let validator = Validator::start();
+    let sender = validator.funded_keypair("sender");
+    let recipient = validator.funded_keypair("recipient");
+    let mint = validator.mint(&sender, 6, 1_000_000_000);
+
+    let created = validator.hadespay(
+        &sender,
+        &[
+            "create",
+            "--id",
+            "grant",
+            "--recipient",
+            &recipient.pubkey().to_string(),
+            "--mint",
+            &mint.to_string(),
+            "--amount",
+            "12.5",
+            "--duration",
+            "50",
+        ],
+    );
+    let stream = created["stream"].as_str().unwrap();
+
+    let shown = validator.hadespay(&recipient, &["show", stream]);
+    assert_eq!(shown["mint"], mint.to_string().as_str());
+    assert_eq!(shown["decimals"], 6);
+    assert_eq!(shown["deposit"], "12.5");
+    assert_eq!(shown["rate"], "0.25");
+
+    let error = validator.hadespay_err(&sender, &["reload", stream, "--amount", "0.0000001"]);
+    assert!(error.contains("more than 6 decimals"), "{error}");
+}
+
+#[test]
+#[ignore = "needs solana-test-validator and target/deploy/stream_contract.so"]
+fn program_errors_are_named() {
+    let validator = Validator::start();
+    let sender = validator.funded_keypair("sender");
+    let recipient = validator.funded_keypair("recipient");
+
+    let created = validator.hadespay(
+        &sender,
+        &[
+            "create",
+            "--id",
+            "fixed",
+            "--recipient",
+            &recipient.pubkey().to_string(),
+            "--amount",
+            "1",
+            "--duration",
+            "100",
+        ],
+    );
+    let stream = created["stream"].as_str().unwrap();
+
+    // Only the sender may pause by default
+    let error = validator.hadespay_err(&recipient, &["pause", stream]);
+    assert!(error.contains("NotAuthorized"), "{error}");
+
+    // Only infinite streams can be reloaded
+    let error = validator.hadespay_err(&sender, &["reload", stream, "--amount", "1"]);
+    assert!(error.contains("NotInfiniteStream"), "{error}");
+}
@@ -0,0 +1,37 @@
+use anchor_lang::solana_program::program::MAX_RETURN_DATA;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Result};
+use stream_contract::{
+    BatchWithdrawal, ProgramConfig, SplitAccount, StreamAccount, StreamStatusView,
+};
+
+/// Decodes the data of a stream account, discriminator included.
+pub fn decode_stream(data: &[u8]) -> Result<StreamAccount> {
+    StreamAccount::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the data of the SplitAccount of a split stream.
+pub fn decode_split(data: &[u8]) -> Result<SplitAccount> {
+    SplitAccount::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the data of the ProgramConfig account.
+pub fn decode_config(data: &[u8]) -> Result<ProgramConfig> {
+    ProgramConfig::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the return data of get_stream_status.
+pub fn decode_status(return_data: &[u8]) -> std::io::Result<StreamStatusView> {
+    decode_return_data(return_data)
+}
+
+/// Decodes the return data of withdraw_from_streams_batch.
+pub fn decode_batch_withdrawals(return_data: &[u8]) -> std::io::Result<Vec<BatchWithdrawal>> {
+    decode_return_data(return_data)
+}
+
+// The runtime trims trailing zero bytes off return data, they are put back before decoding
+fn decode_return_data<T: AnchorDeserialize>(return_data: &[u8]) -> std::io::Result<T> {
+    let mut data = return_data.to_vec();
+    data.resize(MAX_RETURN_DATA.max(return_data.len()), 0);
+    T::deserialize(&mut data.as_slice())
+}
@@ -0,0 +1,127 @@
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::transaction::TransactionError;
+use stream_contract::MyError;
+
+// Every variant in declaration order, Anchor only converts them one way
+const ALL: [MyError; 51] = [
+    MyError::SenderIsRecipient,
+    MyError::DepositIsZero,
+    MyError::IntervalIsZero,
+    MyError::AmountIsZero,
+    MyError::PastStartTime,
+    MyError::FutureStartTime,
+    MyError::DepositSmallerThanTime,
+    MyError::DepositNotMultipleOfTime,
+    MyError::IncorrectStreamId,
+    MyError::TitleTooLong,
+    MyError::NothingToWithdraw,
+    MyError::IncorrectRecipient,
+    MyError::IncorrectSender,
+    MyError::IncorrectTokenAddress,
+    MyError::StreamNotStarted,
+    MyError::StreamIsPaused,
+    MyError::StreamAlreadyPaused,
+    MyError::StreamNotPaused,
+    MyError::StreamAlreadyEnded,
+    MyError::StreamAlreadyCancelled,
+    MyError::StreamNotEmpty,
+    MyError::NotInfiniteStream,
+    MyError::NotAuthorized,
+    MyError::InvalidCancelBy,
+    MyError::InvalidPauseBy,
+    MyError::InvalidWithdrawBy,
+    MyError::InvalidResumeBy,
+    MyError::InvalidEditBy,
+    MyError::IncorrectDuration,
+    MyError::AssociatedTokenAccountIncorrect,
+    MyError::InvalidCliffPercent,
+    MyError::InvalidCliffTime,
+    MyError::CliffNotReached,
+    MyError::MathOverflow,
+    MyError::BalanceUnderflow,
+    MyError::AmountExceedsWithdrawable,
+    MyError::InvalidDestination,
+    MyError::InvalidBatch,
+    MyError::ProgramHalted,
+    MyError::MintNotAllowed,
+    MyError::TooManyMints,
+    MyError::FeeTooHigh,
+    MyError::InvalidTreasury,
+    MyError::IncorrectPartner,
+    MyError::InvalidSplit,
+    MyError::NotInSplit,
+    MyError::SplitStream,
+    MyError::InvalidCheckpoints,
+    MyError::ScheduleNotEditable,
+    MyError::InvalidStateTransition,
+    MyError::CliffExceedsDeposit,
+];
+
+/// The `MyError` variant with custom program error code `code`.
+pub fn from_code(code: u32) -> Option<MyError> {
+    ALL.iter().copied().find(|e| u32::from(*e) == code)
+}
+
+/// The `MyError` an instruction failed with.
+pub fn from_instruction_error(err: &InstructionError) -> Option<MyError> {
+    match err {
+        InstructionError::Custom(code) => from_code(*code),
+        _ => None,
+    }
+}
+
+/// The `MyError` a transaction failed with.
+///
+/// The error does not say which program failed, so transactions that also call other
+/// programs with custom errors in the same range can be decoded wrongly.
+pub fn from_transaction_error(err: &TransactionError) -> Option<MyError> {
+    match err {
+        TransactionError::InstructionError(_, err) => from_instruction_error(err),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_contiguous() {
+        for (i, error) in ALL.iter().enumerate() {
+            assert_eq!(u32::from(*error), 6000 + i as u32, "{}", error.name());
+        }
+        // Released codes never move, retired variants keep their slot
+        assert_eq!(u32::from(MyError::DepositNotMultipleOfTime), 6007);
+    }
+
+    #[test]
+    fn every_variant_is_listed() {
+        // Read from the program source, so a new variant fails here until it is added
+        let source = include_str!("../../programs/stream_contract/src/errors.rs");
+        let declared: Vec<&str> = source
+            .split_once("pub enum MyError {")
+            .unwrap()
+            .1
+            .lines()
+            .map(str::trim)
+            .filter_map(|line| line.strip_suffix(','))
+            .filter(|line| !line.starts_with('#'))
+            .collect();
+        let listed: Vec<String> = ALL.iter().map(|e| e.name()).collect();
+        assert_eq!(listed, declared);
+    }
+
+    #[test]
+    fn decodes_transaction_errors() {
+        let err = TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(u32::from(MyError::NothingToWithdraw)),
+        );
+        assert_eq!(
+            from_transaction_error(&err).map(|e| e.name()),
+            Some(MyError::NothingToWithdraw.name())
+        );
+        assert!(from_code(6000 + ALL.len() as u32).is_none());
+        assert!(from_transaction_error(&TransactionError::AccountNotFound).is_none());
+    }
+}
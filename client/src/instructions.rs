@@ -0,0 +1,743 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::{system_program, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token;
+use stream_contract::{
+    accounts, instruction, Checkpoint, CreateStreamParams, SplitRecipient, StreamAccount, ID,
+};
+
+use crate::pda;
+
+/// What a stream pays out, picks between the native and the token instructions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Asset {
+    Native,
+    Token { mint: Pubkey, token_program: Pubkey },
+}
+
+impl Asset {
+    /// Asset of an existing stream, `token_program` is only used for token streams.
+    pub fn of(stream: &StreamAccount, token_program: Pubkey) -> Self {
+        if stream.is_native() {
+            Asset::Native
+        } else {
+            Asset::Token {
+                mint: stream.token_address,
+                token_program,
+            }
+        }
+    }
+}
+
+/// Keys of an existing stream, everything its instructions derive their accounts from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamRef {
+    pub address: Pubkey,
+    pub stream_id: String,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    // Only set when the stream pays a partner fee
+    pub partner: Option<Pubkey>,
+    pub asset: Asset,
+}
+
+impl StreamRef {
+    /// Keys of a decoded stream, `token_program` must own the mint of token streams.
+    pub fn new(stream: &StreamAccount, token_program: Pubkey) -> Self {
+        StreamRef {
+            address: pda::stream_address(&stream.stream_id, &stream.sender),
+            stream_id: stream.stream_id.clone(),
+            sender: stream.sender,
+            recipient: stream.recipient,
+            partner: (stream.partner_fee_bps > 0).then_some(stream.partner),
+            asset: Asset::of(stream, token_program),
+        }
+    }
+
+    fn vault(&self, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+        pda::vault_address(&self.address, mint, token_program)
+    }
+}
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn ata(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    pda::token_account_address(owner, mint, token_program)
+}
+
+/// Signed by the upgrade authority of the program, which becomes the admin.
+pub fn initialize_config(admin: &Pubkey) -> Instruction {
+    build(
+        accounts::InitializeConfig {
+            config: pda::config_address(),
+            admin: *admin,
+            program_data: pda::program_data_address(),
+            system_program: system_program::ID,
+        },
+        instruction::InitializeConfig {},
+    )
+}
+
+pub fn update_config(
+    admin: &Pubkey,
+    is_halted: bool,
+    allowed_mints: Vec<Pubkey>,
+    fee_bps: u64,
+    treasury: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            config: pda::config_address(),
+            admin: *admin,
+        },
+        instruction::UpdateConfig {
+            is_halted,
+            allowed_mints,
+            fee_bps,
+            treasury: *treasury,
+        },
+    )
+}
+
+pub fn transfer_admin(admin: &Pubkey, new_admin: &Pubkey) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            config: pda::config_address(),
+            admin: *admin,
+        },
+        instruction::TransferAdmin {
+            new_admin: *new_admin,
+        },
+    )
+}
+
+/// Signed by the admin proposed with transfer_admin.
+pub fn accept_admin(new_admin: &Pubkey) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            config: pda::config_address(),
+            admin: *new_admin,
+        },
+        instruction::AcceptAdmin {},
+    )
+}
+
+/// create_stream or create_stream_token, token deposits come from the sender's
+/// associated token account.
+pub fn create_stream(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    asset: &Asset,
+    params: &CreateStreamParams,
+) -> Instruction {
+    let stream = pda::stream_address(&params.stream_id, sender);
+
+    match asset {
+        Asset::Native => build(
+            accounts::CreateStream {
+                stream,
+                sender: *sender,
+                recipient: *recipient,
+                system_program: system_program::ID,
+                config: pda::config_address(),
+            },
+            instruction::CreateStream {
+                params: params.clone(),
+            },
+        ),
+        Asset::Token {
+            mint,
+            token_program,
+        } => build(
+            accounts::CreateStreamToken {
+                stream,
+                sender: *sender,
+                recipient: *recipient,
+                token_address: *mint,
+                sender_tokens: ata(sender, mint, token_program),
+                stream_tokens: pda::vault_address(&stream, mint, token_program),
+                token_program: *token_program,
+                system_program: system_program::ID,
+                associated_token_program: associated_token::ID,
+                rent: sysvar::rent::ID,
+                config: pda::config_address(),
+            },
+            instruction::CreateStreamToken {
+                params: params.clone(),
+            },
+        ),
+    }
+}
+
+/// create_step_stream or create_step_stream_token.
+pub fn create_step_stream(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    asset: &Asset,
+    params: &CreateStreamParams,
+    checkpoints: Vec<Checkpoint>,
+) -> Instruction {
+    let stream = pda::stream_address(&params.stream_id, sender);
+
+    match asset {
+        Asset::Native => build(
+            accounts::CreateStepStream {
+                stream,
+                sender: *sender,
+                recipient: *recipient,
+                system_program: system_program::ID,
+                config: pda::config_address(),
+            },
+            instruction::CreateStepStream {
+                params: params.clone(),
+                checkpoints,
+            },
+        ),
+        Asset::Token {
+            mint,
+            token_program,
+        } => build(
+            accounts::CreateStepStreamToken {
+                stream,
+                sender: *sender,
+                recipient: *recipient,
+                token_address: *mint,
+                sender_tokens: ata(sender, mint, token_program),
+                stream_tokens: pda::vault_address(&stream, mint, token_program),
+                token_program: *token_program,
+                system_program: system_program::ID,
+                associated_token_program: associated_token::ID,
+                rent: sysvar::rent::ID,
+                config: pda::config_address(),
+            },
+            instruction::CreateStepStreamToken {
+                params: params.clone(),
+                checkpoints,
+            },
+        ),
+    }
+}
+
+/// create_split_stream or create_split_stream_token, the recipient of the stream is its
+/// SplitAccount.
+pub fn create_split_stream(
+    sender: &Pubkey,
+    asset: &Asset,
+    params: &CreateStreamParams,
+    recipients: Vec<SplitRecipient>,
+) -> Instruction {
+    let stream = pda::stream_address(&params.stream_id, sender);
+    let split = pda::split_address(&stream);
+
+    match asset {
+        Asset::Native => build(
+            accounts::CreateSplitStream {
+                stream,
+                split,
+                sender: *sender,
+                system_program: system_program::ID,
+                config: pda::config_address(),
+            },
+            instruction::CreateSplitStream {
+                params: params.clone(),
+                recipients,
+            },
+        ),
+        Asset::Token {
+            mint,
+            token_program,
+        } => build(
+            accounts::CreateSplitStreamToken {
+                stream,
+                split,
+                sender: *sender,
+                token_address: *mint,
+                sender_tokens: ata(sender, mint, token_program),
+                stream_tokens: pda::vault_address(&stream, mint, token_program),
+                token_program: *token_program,
+                system_program: system_program::ID,
+                associated_token_program: associated_token::ID,
+                rent: sysvar::rent::ID,
+                config: pda::config_address(),
+            },
+            instruction::CreateSplitStreamToken {
+                params: params.clone(),
+                recipients,
+            },
+        ),
+    }
+}
+
+/// create_streams_batch, one token stream per recipient and params.
+pub fn create_streams_batch(
+    sender: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    streams: &[(Pubkey, CreateStreamParams)],
+) -> Instruction {
+    let mut ix = build(
+        accounts::CreateStreamsBatch {
+            sender: *sender,
+            token_address: *mint,
+            sender_tokens: ata(sender, mint, token_program),
+            token_program: *token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            config: pda::config_address(),
+        },
+        instruction::CreateStreamsBatch {
+            params: streams.iter().map(|(_, params)| params.clone()).collect(),
+        },
+    );
+
+    for (recipient, params) in streams {
+        let stream = pda::stream_address(&params.stream_id, sender);
+        ix.accounts.extend([
+            AccountMeta::new(stream, false),
+            AccountMeta::new_readonly(*recipient, false),
+            AccountMeta::new(pda::vault_address(&stream, mint, token_program), false),
+        ]);
+    }
+    ix
+}
+
+/// withdraw_from_stream or withdraw_from_stream_token, paid to the recipient's wallet or
+/// associated token account. `amount` of None withdraws everything withdrawable.
+///
+/// Like every instruction that pays fees it takes the treasury of the config, which is
+/// left out when the protocol charges none, see `ProgramConfig::fee_treasury`.
+pub fn withdraw(
+    stream: &StreamRef,
+    authority: &Pubkey,
+    treasury: Option<&Pubkey>,
+    amount: Option<u64>,
+) -> Instruction {
+    match &stream.asset {
+        Asset::Native => build(
+            accounts::WithdrawFromStream {
+                stream: stream.address,
+                authority: *authority,
+                recipient: stream.recipient,
+                system_program: system_program::ID,
+                treasury: treasury.copied(),
+                partner: stream.partner,
+                config: pda::config_address(),
+            },
+            instruction::WithdrawFromStream {
+                _stream_id: stream.stream_id.clone(),
+                amount,
+            },
+        ),
+        Asset::Token {
+            mint,
+            token_program,
+        } => build(
+            accounts::WithdrawFromStreamToken {
+                stream: stream.address,
+                stream_tokens: stream.vault(mint, token_program),
+                authority: *authority,
+                sender: stream.sender,
+                recipient: stream.recipient,
+                recipient_tokens: ata(&stream.recipient, mint, token_program),
+                token_address: *mint,
+                token_program: *token_program,
+                system_program: system_program::ID,
+                associated_token_program: associated_token::ID,
+                rent: sysvar::rent::ID,
+                treasury: treasury.copied(),
+                treasury_tokens: treasury.map(|t| ata(t, mint, token_program)),
+                partner: stream.partner,
+                partner_tokens: stream.partner.map(|p| ata(&p, mint, token_program)),
+                config: pda::config_address(),
+            },
+            instruction::WithdrawFromStreamToken {
+                _stream_id: stream.stream_id.clone(),
+                amount,
+            },
+        ),
+    }
+}
+
+/// withdraw_from_stream_to or withdraw_from_stream_token_to, signed by the recipient.
+/// `destination` is a wallet for native streams and a token account of the mint for
+/// token streams.
+pub fn withdraw_to(
+    stream: &StreamRef,
+    destination: &Pubkey,
+    treasury: Option<&Pubkey>,
+    amount: Option<u64>,
+) -> Instruction {
+    match &stream.asset {
+        Asset::Native => build(
+            accounts::WithdrawFromStreamTo {
+                stream: stream.address,
+                recipient: stream.recipient,
+                destination: *destination,
+                system_program: system_program::ID,
+                treasury: treasury.copied(),
+                partner: stream.partner,
+                config: pda::config_address(),
+            },
+            instruction::WithdrawFromStreamTo {
+                _stream_id: stream.stream_id.clone(),
+                amount,
+            },
+        ),
+        Asset::Token {
+            mint,
+            token_program,
+        } => build(
+            accounts::WithdrawFromStreamTokenTo {
+                stream: stream.address,
+                stream_tokens: stream.vault(mint, token_program),
+                recipient: stream.recipient,
+                sender: stream.sender,
+                destination_tokens: *destination,
+                token_address: *mint,
+                treasury: treasury.copied(),
+                treasury_tokens: treasury.map(|t| ata(t, mint, token_program)),
+                partner: stream.partner,
+                partner_tokens: stream.partner.map(|p| ata(&p, mint, token_program)),
+                token_program: *token_program,
+                system_program: system_program::ID,
+                associated_token_program: associated_token::ID,
+                rent: sysvar::rent::ID,
+                config: pda::config_address(),
+            },
+            instruction::WithdrawFromStreamTokenTo {
+                _stream_id: stream.stream_id.clone(),
+                amount,
+            },
+        ),
+    }
+}
+
+/// withdraw_from_split or withdraw_from_split_token, pays `recipient` its share.
+pub fn withdraw_share(
+    stream: &StreamRef,
+    recipient: &Pubkey,
+    treasury: Option<&Pubkey>,
+) -> Instruction {
+    let split = pda::split_address(&stream.address);
+
+    match &stream.asset {
+        Asset::Native => build(
+            accounts::WithdrawFromSplit {
+                stream: stream.address,
+                split,
+                recipient: *recipient,
+                system_program: system_program::ID,
+                treasury: treasury.copied(),
+                partner: stream.partner,
+                config: pda::config_address(),
+            },
+            instruction::WithdrawFromSplit {
+                _stream_id: stream.stream_id.clone(),
+            },
+        ),
+        Asset::Token {
+            mint,
+            token_program,
+        } => build(
+            accounts::WithdrawFromSplitToken {
+                stream: stream.address,
+                split,
+                stream_tokens: stream.vault(mint, token_program),
+                recipient: *recipient,
+                recipient_tokens: ata(recipient, mint, token_program),
+                token_address: *mint,
+                treasury: treasury.copied(),
+                treasury_tokens: treasury.map(|t| ata(t, mint, token_program)),
+                partner: stream.partner,
+                partner_tokens: stream.partner.map(|p| ata(&p, mint, token_program)),
+                token_program: *token_program,
+                system_program: system_program::ID,
+                associated_token_program: associated_token::ID,
+                config: pda::config_address(),
+            },
+            instruction::WithdrawFromSplitToken {
+                _stream_id: stream.stream_id.clone(),
+            },
+        ),
+    }
+}
+
+/// withdraw_from_streams_batch over token streams of `recipient` under `token_program`,
+/// paid to its associated token accounts. The treasury and partner token accounts must
+/// exist already.
+///
+/// # Panics
+///
+/// When one of `streams` is a native stream.
+pub fn withdraw_batch(
+    recipient: &Pubkey,
+    token_program: &Pubkey,
+    treasury: Option<&Pubkey>,
+    streams: &[StreamRef],
+) -> Instruction {
+    let mut ix = build(
+        accounts::WithdrawFromStreamsBatch {
+            recipient: *recipient,
+            token_program: *token_program,
+            config: pda::config_address(),
+        },
+        instruction::WithdrawFromStreamsBatch {},
+    );
+
+    for stream in streams {
+        let Asset::Token { mint, .. } = stream.asset else {
+            panic!("native stream {} in a token batch", stream.address);
+        };
+        ix.accounts.extend([
+            AccountMeta::new(stream.address, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new(stream.vault(&mint, token_program), false),
+            AccountMeta::new(ata(recipient, &mint, token_program), false),
+            match treasury {
+                Some(treasury) => AccountMeta::new(ata(treasury, &mint, token_program), false),
+                None => AccountMeta::new_readonly(ID, false),
+            },
+            match stream.partner {
+                Some(partner) => AccountMeta::new(ata(&partner, &mint, token_program), false),
+                None => AccountMeta::new_readonly(ID, false),
+            },
+        ]);
+    }
+    ix
+}
+
+/// cancel_stream or cancel_stream_token, refunds go to the sender's wallet or associated
+/// token account.
+pub fn cancel(stream: &StreamRef, authority: &Pubkey, treasury: Option<&Pubkey>) -> Instruction {
+    match &stream.asset {
+        Asset::Native => build(
+            accounts::CancelStream {
+                stream: stream.address,
+                authority: *authority,
+                sender: stream.sender,
+                recipient: stream.recipient,
+                system_program: system_program::ID,
+                treasury: treasury.copied(),
+                partner: stream.partner,
+                config: pda::config_address(),
+            },
+            instruction::CancelStream {
+                _stream_id: stream.stream_id.clone(),
+            },
+        ),
+        Asset::Token {
+            mint,
+            token_program,
+        } => build(
+            accounts::CancelStreamToken {
+                stream: stream.address,
+                stream_tokens: stream.vault(mint, token_program),
+                authority: *authority,
+                recipient: stream.recipient,
+                recipient_tokens: ata(&stream.recipient, mint, token_program),
+                sender_tokens: ata(&stream.sender, mint, token_program),
+                token_address: *mint,
+                token_program: *token_program,
+                system_program: system_program::ID,
+                associated_token_program: associated_token::ID,
+                rent: sysvar::rent::ID,
+                treasury: treasury.copied(),
+                treasury_tokens: treasury.map(|t| ata(t, mint, token_program)),
+                partner: stream.partner,
+                partner_tokens: stream.partner.map(|p| ata(&p, mint, token_program)),
+                config: pda::config_address(),
+            },
+            instruction::CancelStreamToken {
+                _stream_id: stream.stream_id.clone(),
+            },
+        ),
+    }
+}
+
+/// pause_stream or pause_stream_token.
+pub fn pause(stream: &StreamRef, authority: &Pubkey, treasury: Option<&Pubkey>) -> Instruction {
+    match &stream.asset {
+        Asset::Native => build(
+            accounts::PauseStream {
+                stream: stream.address,
+                authority: *authority,
+                recipient: stream.recipient,
+                system_program: system_program::ID,
+                treasury: treasury.copied(),
+                partner: stream.partner,
+                config: pda::config_address(),
+            },
+            instruction::PauseStream {
+                _stream_id: stream.stream_id.clone(),
+            },
+        ),
+        Asset::Token {
+            mint,
+            token_program,
+        } => build(
+            accounts::PauseStreamToken {
+                stream: stream.address,
+                stream_tokens: stream.vault(mint, token_program),
+                authority: *authority,
+                sender: stream.sender,
+                recipient: stream.recipient,
+                recipient_tokens: ata(&stream.recipient, mint, token_program),
+                token_address: *mint,
+                token_program: *token_program,
+                system_program: system_program::ID,
+                associated_token_program: associated_token::ID,
+                rent: sysvar::rent::ID,
+                treasury: treasury.copied(),
+                treasury_tokens: treasury.map(|t| ata(t, mint, token_program)),
+                partner: stream.partner,
+                partner_tokens: stream.partner.map(|p| ata(&p, mint, token_program)),
+                config: pda::config_address(),
+            },
+            instruction::PauseStreamToken {
+                _stream_id: stream.stream_id.clone(),
+            },
+        ),
+    }
+}
+
+/// edit_stream or edit_stream_token.
+pub fn edit(
+    stream: &StreamRef,
+    authority: &Pubkey,
+    treasury: Option<&Pubkey>,
+    stream_title: String,
+    interval: u64,
+    rate: u64,
+    duration: u64,
+) -> Instruction {
+    match &stream.asset {
+        Asset::Native => build(
+            accounts::EditStream {
+                stream: stream.address,
+                authority: *authority,
+                recipient: stream.recipient,
+                system_program: system_program::ID,
+                treasury: treasury.copied(),
+                partner: stream.partner,
+                config: pda::config_address(),
+            },
+            instruction::EditStream {
+                _stream_id: stream.stream_id.clone(),
+                stream_title,
+                interval,
+                rate,
+                duration,
+            },
+        ),
+        Asset::Token {
+            mint,
+            token_program,
+        } => build(
+            accounts::EditStreamToken {
+                stream: stream.address,
+                stream_tokens: stream.vault(mint, token_program),
+                authority: *authority,
+                sender: stream.sender,
+                recipient: stream.recipient,
+                recipient_tokens: ata(&stream.recipient, mint, token_program),
+                token_address: *mint,
+                token_program: *token_program,
+                system_program: system_program::ID,
+                associated_token_program: associated_token::ID,
+                rent: sysvar::rent::ID,
+                treasury: treasury.copied(),
+                treasury_tokens: treasury.map(|t| ata(t, mint, token_program)),
+                partner: stream.partner,
+                partner_tokens: stream.partner.map(|p| ata(&p, mint, token_program)),
+                config: pda::config_address(),
+            },
+            instruction::EditStreamToken {
+                _stream_id: stream.stream_id.clone(),
+                stream_title,
+                interval,
+                rate,
+                duration,
+            },
+        ),
+    }
+}
+
+pub fn resume(stream: &StreamRef, authority: &Pubkey) -> Instruction {
+    build(
+        accounts::ResumeStream {
+            stream: stream.address,
+            authority: *authority,
+            system_program: system_program::ID,
+            config: pda::config_address(),
+        },
+        instruction::ResumeStream {
+            _stream_id: stream.stream_id.clone(),
+        },
+    )
+}
+
+/// reload_stream or reload_stream_token, tokens come from the sender's associated token
+/// account.
+pub fn reload(stream: &StreamRef, amount: u64) -> Instruction {
+    match &stream.asset {
+        Asset::Native => build(
+            accounts::ReloadStream {
+                stream: stream.address,
+                sender: stream.sender,
+                system_program: system_program::ID,
+                config: pda::config_address(),
+            },
+            instruction::ReloadStream {
+                _stream_id: stream.stream_id.clone(),
+                amount,
+            },
+        ),
+        Asset::Token {
+            mint,
+            token_program,
+        } => build(
+            accounts::ReloadStreamToken {
+                stream: stream.address,
+                stream_tokens: stream.vault(mint, token_program),
+                sender: stream.sender,
+                sender_tokens: ata(&stream.sender, mint, token_program),
+                token_address: *mint,
+                token_program: *token_program,
+                config: pda::config_address(),
+            },
+            instruction::ReloadStreamToken {
+                _stream_id: stream.stream_id.clone(),
+                amount,
+            },
+        ),
+    }
+}
+
+/// get_stream_status, meant to be simulated and decoded with `accounts::decode_status`.
+pub fn get_stream_status(stream: &Pubkey) -> Instruction {
+    build(
+        accounts::GetStreamStatus { stream: *stream },
+        instruction::GetStreamStatus {},
+    )
+}
+
+pub fn delete(stream: &StreamRef) -> Instruction {
+    build(
+        accounts::DeleteStream {
+            stream: stream.address,
+            sender: stream.sender,
+            system_program: system_program::ID,
+            config: pda::config_address(),
+        },
+        instruction::DeleteStream {
+            _stream_id: stream.stream_id.clone(),
+        },
+    )
+}
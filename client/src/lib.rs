@@ -0,0 +1,18 @@
+//! Client side of the stream_contract program.
+//!
+//! `instructions` builds every instruction of the program and derives the accounts it
+//! needs, `pda` holds the addresses the program derives, `accounts` decodes its accounts
+//! and return data, and `errors` maps failed transactions back to `MyError`.
+
+pub mod accounts;
+pub mod errors;
+pub mod instructions;
+pub mod pda;
+
+pub use instructions::{Asset, StreamRef};
+pub use stream_contract::{interval_duration, mul_div_round};
+pub use stream_contract::{
+    BatchWithdrawal, Checkpoint, CreateStreamParams, MyError, ProgramConfig, RunState,
+    ScheduleKind, SplitAccount, SplitRecipient, SplitShare, StateChangeAuth, StreamAccount,
+    StreamAction, StreamPhase, StreamStatus, StreamStatusView, ID,
+};
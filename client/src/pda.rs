@@ -0,0 +1,34 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use stream_contract::{CONFIG_SEED, ID, SPLIT_SEED};
+
+/// The ProgramConfig PDA.
+pub fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[CONFIG_SEED], &ID).0
+}
+
+/// The ProgramData account of the program, holding its upgrade authority.
+pub fn program_data_address() -> Pubkey {
+    Pubkey::find_program_address(&[ID.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+/// The StreamAccount PDA of `stream_id` created by `sender`.
+pub fn stream_address(stream_id: &str, sender: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[stream_id.as_bytes(), sender.as_ref()], &ID).0
+}
+
+/// The SplitAccount PDA of a split stream, which is also its recipient.
+pub fn split_address(stream: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SPLIT_SEED, stream.as_ref()], &ID).0
+}
+
+/// The associated token account of `owner` for `mint` under `token_program`.
+pub fn token_account_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, token_program)
+}
+
+/// The account holding the tokens of a token stream.
+pub fn vault_address(stream: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    token_account_address(stream, mint, token_program)
+}
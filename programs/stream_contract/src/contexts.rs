@@ -0,0 +1,927 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::errors::MyError;
+use crate::state::*;
+use crate::{CONFIG_SEED, SPLIT_SEED};
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        seeds = [CONFIG_SEED],
+        bump,
+        payer = admin,
+        space = 8 + ProgramConfig::MAX_SIZE
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    // Only the upgrade authority of the program can become the admin
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID,
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ MyError::NotAuthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(mut, seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+    // Current admin, or the pending admin when accepting
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: CreateStreamParams)]
+pub struct CreateStream<'info> {
+    // stream_account Account PDA
+    #[account(
+        init,
+        seeds = [params.stream_id.as_bytes(), sender.key().as_ref()],
+        bump,
+        payer = sender,
+        space = 16 + StreamAccount::MAX_SIZE
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: CreateStreamParams)]
+pub struct CreateStreamToken<'info> {
+    // stream_account Account PDA
+    #[account(
+        init,
+        seeds = [params.stream_id.as_bytes(), sender.key().as_ref()],
+        bump,
+        payer = sender,
+        space = 16 + StreamAccount::MAX_SIZE
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    pub token_address: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub sender_tokens: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(init_if_needed,
+        payer = sender,
+        associated_token::mint = token_address,
+        associated_token::authority = stream,
+        associated_token::token_program = token_program)]
+    pub stream_tokens: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: CreateStreamParams, checkpoints: Vec<Checkpoint>)]
+pub struct CreateStepStream<'info> {
+    // stream_account Account PDA, sized for its checkpoints
+    #[account(
+        init,
+        seeds = [params.stream_id.as_bytes(), sender.key().as_ref()],
+        bump,
+        payer = sender,
+        space = 16 + StreamAccount::MAX_SIZE + checkpoints.len() * Checkpoint::SIZE
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    /// CHECK: safe
+    pub recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: CreateStreamParams, checkpoints: Vec<Checkpoint>)]
+pub struct CreateStepStreamToken<'info> {
+    // stream_account Account PDA, sized for its checkpoints
+    #[account(
+        init,
+        seeds = [params.stream_id.as_bytes(), sender.key().as_ref()],
+        bump,
+        payer = sender,
+        space = 16 + StreamAccount::MAX_SIZE + checkpoints.len() * Checkpoint::SIZE
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    /// CHECK: safe
+    pub recipient: AccountInfo<'info>,
+    pub token_address: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub sender_tokens: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(init_if_needed,
+        payer = sender,
+        associated_token::mint = token_address,
+        associated_token::authority = stream,
+        associated_token::token_program = token_program)]
+    pub stream_tokens: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: CreateStreamParams)]
+pub struct CreateSplitStream<'info> {
+    // stream_account Account PDA
+    #[account(
+        init,
+        seeds = [params.stream_id.as_bytes(), sender.key().as_ref()],
+        bump,
+        payer = sender,
+        space = 16 + StreamAccount::MAX_SIZE
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    // Recipients of the stream, which is the stream's recipient
+    #[account(
+        init,
+        seeds = [SPLIT_SEED, stream.key().as_ref()],
+        bump,
+        payer = sender,
+        space = 8 + SplitAccount::MAX_SIZE
+    )]
+    pub split: Account<'info, SplitAccount>,
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: CreateStreamParams)]
+pub struct CreateSplitStreamToken<'info> {
+    // stream_account Account PDA
+    #[account(
+        init,
+        seeds = [params.stream_id.as_bytes(), sender.key().as_ref()],
+        bump,
+        payer = sender,
+        space = 16 + StreamAccount::MAX_SIZE
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    // Recipients of the stream, which is the stream's recipient
+    #[account(
+        init,
+        seeds = [SPLIT_SEED, stream.key().as_ref()],
+        bump,
+        payer = sender,
+        space = 8 + SplitAccount::MAX_SIZE
+    )]
+    pub split: Account<'info, SplitAccount>,
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    pub token_address: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub sender_tokens: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(init_if_needed,
+        payer = sender,
+        associated_token::mint = token_address,
+        associated_token::authority = stream,
+        associated_token::token_program = token_program)]
+    pub stream_tokens: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct CreateStreamsBatch<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    pub token_address: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = token_address,
+        token::token_program = token_program,
+        token::authority = sender
+    )]
+    pub sender_tokens: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct WithdrawFromStream<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        has_one = recipient @ MyError::IncorrectRecipient
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(mut, address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(mut, address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct WithdrawFromStreamToken<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        has_one = recipient @ MyError::IncorrectRecipient,
+        has_one = sender @ MyError::IncorrectSender,
+        has_one = token_address @ MyError::IncorrectTokenAddress
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(
+        mut,
+        token::mint = token_address,
+        token::token_program = token_program,
+        token::authority = stream
+    )]
+    pub stream_tokens: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub sender: AccountInfo<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program)]
+    pub recipient_tokens: InterfaceAccount<'info, TokenAccount>,
+    pub token_address: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program)]
+    pub treasury_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = partner,
+        associated_token::token_program = token_program)]
+    pub partner_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct WithdrawFromStreamTo<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        has_one = recipient @ MyError::NotAuthorized
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+    /// CHECK: any system account chosen by the recipient
+    #[account(
+        mut,
+        constraint = *destination.owner == System::id() @ MyError::InvalidDestination
+    )]
+    pub destination: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(mut, address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(mut, address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct WithdrawFromStreamTokenTo<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        has_one = recipient @ MyError::NotAuthorized,
+        has_one = sender @ MyError::IncorrectSender,
+        has_one = token_address @ MyError::IncorrectTokenAddress
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(
+        mut,
+        token::mint = token_address,
+        token::token_program = token_program,
+        token::authority = stream
+    )]
+    pub stream_tokens: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+    /// CHECK: safe
+    pub sender: AccountInfo<'info>,
+    #[account(
+        mut,
+        token::mint = token_address,
+        token::token_program = token_program,
+        constraint = destination_tokens.key() != stream_tokens.key() @ MyError::InvalidDestination
+    )]
+    pub destination_tokens: InterfaceAccount<'info, TokenAccount>,
+    pub token_address: InterfaceAccount<'info, Mint>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = recipient,
+        associated_token::mint = token_address,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program)]
+    pub treasury_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = recipient,
+        associated_token::mint = token_address,
+        associated_token::authority = partner,
+        associated_token::token_program = token_program)]
+    pub partner_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct WithdrawFromSplit<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        constraint = stream.recipient == split.key() @ MyError::IncorrectRecipient
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut, seeds = [SPLIT_SEED, stream.key().as_ref()], bump = split.bump)]
+    pub split: Account<'info, SplitAccount>,
+    // One of the recipients of the split
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(mut, address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(mut, address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct WithdrawFromSplitToken<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        constraint = stream.recipient == split.key() @ MyError::IncorrectRecipient,
+        has_one = token_address @ MyError::IncorrectTokenAddress
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut, seeds = [SPLIT_SEED, stream.key().as_ref()], bump = split.bump)]
+    pub split: Account<'info, SplitAccount>,
+    #[account(
+        mut,
+        token::mint = token_address,
+        token::token_program = token_program,
+        token::authority = stream
+    )]
+    pub stream_tokens: InterfaceAccount<'info, TokenAccount>,
+    // One of the recipients of the split
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+    #[account(init_if_needed,
+        payer = recipient,
+        associated_token::mint = token_address,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program)]
+    pub recipient_tokens: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_address: InterfaceAccount<'info, Mint>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = recipient,
+        associated_token::mint = token_address,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program)]
+    pub treasury_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = recipient,
+        associated_token::mint = token_address,
+        associated_token::authority = partner,
+        associated_token::token_program = token_program)]
+    pub partner_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromStreamsBatch<'info> {
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct CancelStream<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        has_one = recipient @ MyError::IncorrectRecipient,
+        has_one = sender @ MyError::IncorrectSender
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub sender: AccountInfo<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(mut, address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(mut, address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct CancelStreamToken<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        has_one = recipient @ MyError::IncorrectRecipient,
+        has_one = token_address @ MyError::IncorrectTokenAddress
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(
+        mut,
+        token::mint = token_address,
+        token::token_program = token_program,
+        token::authority = stream
+    )]
+    pub stream_tokens: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program)]
+    pub recipient_tokens: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = token_address,
+        token::token_program = token_program,
+        constraint = sender_tokens.owner == stream.sender @ MyError::IncorrectSender
+    )]
+    pub sender_tokens: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_address: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program)]
+    pub treasury_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = partner,
+        associated_token::token_program = token_program)]
+    pub partner_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct PauseStream<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        has_one = recipient @ MyError::IncorrectRecipient
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(mut, address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(mut, address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct PauseStreamToken<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        has_one = recipient @ MyError::IncorrectRecipient,
+        has_one = sender @ MyError::IncorrectSender,
+        has_one = token_address @ MyError::IncorrectTokenAddress
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(
+        mut,
+        token::mint = token_address,
+        token::token_program = token_program,
+        token::authority = stream
+    )]
+    pub stream_tokens: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub sender: AccountInfo<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program)]
+    pub recipient_tokens: InterfaceAccount<'info, TokenAccount>,
+    pub token_address: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program)]
+    pub treasury_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = partner,
+        associated_token::token_program = token_program)]
+    pub partner_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct EditStream<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        has_one = recipient @ MyError::IncorrectRecipient
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(mut, address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(mut, address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct EditStreamToken<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        has_one = recipient @ MyError::IncorrectRecipient,
+        has_one = sender @ MyError::IncorrectSender,
+        has_one = token_address @ MyError::IncorrectTokenAddress
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(
+        mut,
+        token::mint = token_address,
+        token::token_program = token_program,
+        token::authority = stream
+    )]
+    pub stream_tokens: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub sender: AccountInfo<'info>,
+    /// CHECK: safe
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program)]
+    pub recipient_tokens: InterfaceAccount<'info, TokenAccount>,
+    pub token_address: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    /// CHECK: checked against the config, only passed when the protocol charges a fee
+    #[account(address = config.treasury @ MyError::InvalidTreasury)]
+    pub treasury: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program)]
+    pub treasury_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// CHECK: checked against the stream, only passed when the stream pays a partner fee
+    #[account(address = stream.partner @ MyError::IncorrectPartner)]
+    pub partner: Option<AccountInfo<'info>>,
+    #[account(init_if_needed,
+        payer = authority,
+        associated_token::mint = token_address,
+        associated_token::authority = partner,
+        associated_token::token_program = token_program)]
+    pub partner_tokens: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct ResumeStream<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct ReloadStream<'info> {
+    // stream_account Account PDA
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct ReloadStreamToken<'info> {
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId,
+        has_one = token_address @ MyError::IncorrectTokenAddress
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(
+        mut,
+        token::mint = token_address,
+        token::token_program = token_program,
+        token::authority = stream
+    )]
+    pub stream_tokens: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    #[account(mut)]
+    pub sender_tokens: InterfaceAccount<'info, TokenAccount>,
+    pub token_address: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+// Read only, works while the program is halted
+#[derive(Accounts)]
+pub struct GetStreamStatus<'info> {
+    pub stream: Account<'info, StreamAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_id: String)]
+pub struct DeleteStream<'info> {
+    // stream_account Account PDA
+    #[account(
+        mut,
+        constraint = stream.stream_id == stream_id @ MyError::IncorrectStreamId
+    )]
+    pub stream: Account<'info, StreamAccount>,
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.is_halted @ MyError::ProgramHalted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
@@ -0,0 +1,1077 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_lang::AccountsClose;
+
+use crate::errors::MyError;
+use crate::events::*;
+use crate::schedule::{interval_duration, mul_div_round};
+use crate::state::*;
+use crate::status::{StreamAction, StreamStatus};
+use crate::vault::AssetVault;
+use crate::MAX_BPS;
+
+/// Validates the parameters of a new stream and builds its account state.
+pub fn build_stream_account(
+    params: &CreateStreamParams,
+    sender: Pubkey,
+    recipient: Pubkey,
+    token_address: Pubkey,
+    bump: u8,
+    timestamp: u64,
+) -> Result<StreamAccount> {
+    let mut amount = params.amount;
+    let start = check_stream_params(params, sender, recipient, timestamp)?;
+    let interval = params.interval;
+    let rate = params.rate;
+    let duration = params.duration;
+
+    // Interval of Stream should be greater than 0
+    require!(interval > 0, MyError::IntervalIsZero);
+
+    // Percentage cliffs are given in basis points of the deposit
+    let mut cliff_amount = params.cliff_amount;
+    if params.is_cliff_percent {
+        require!(cliff_amount <= MAX_BPS, MyError::InvalidCliffPercent);
+        cliff_amount = (amount as u128 * cliff_amount as u128 / MAX_BPS as u128) as u64;
+    }
+
+    // Adjust for cliff amount
+    require!(cliff_amount <= amount, MyError::CliffExceedsDeposit);
+    let deposit = amount;
+    amount -= cliff_amount;
+
+    // Amount to Stream should be greater than the Rate of Stream
+    require!(amount >= rate, MyError::DepositSmallerThanTime);
+
+    // Stop Time has to come after the Start Time
+    require!(duration > 0, MyError::FutureStartTime);
+
+    // An interval stream stops the moment its last interval vests. A linear one vests
+    // every second until start + duration, its rate only sets how far a reload extends it.
+    if !params.is_linear {
+        require!(
+            interval_duration(amount, interval, rate) == Some(duration),
+            MyError::IncorrectDuration
+        );
+    }
+    let stop = start.checked_add(duration).ok_or(MyError::MathOverflow)?;
+
+    // Cliff date defaults to the start and must fall within the stream
+    let cliff_time = match params.cliff_time {
+        0 => start,
+        _ => params.cliff_time,
+    };
+    require!(
+        cliff_time >= start && cliff_time <= stop,
+        MyError::InvalidCliffTime
+    );
+
+    Ok(StreamAccount {
+        stream_id: params.stream_id.clone(),
+        stream_title: params.stream_title.clone(),
+        recipient,
+        sender,
+        token_address,
+        create_time: timestamp,
+        start_time: start,
+        stop_time: stop,
+        remaining_balance: deposit,
+        deposit,
+        withdrawn: 0,
+        cliff_amount,
+        cliff_time,
+        cliff_time_left: 0,
+        interval,
+        rate_of_stream: rate,
+        time_left: 0,
+        paused_amount: 0,
+        protocol_fee_paid: 0,
+        partner: params.partner.unwrap_or_default(),
+        partner_fee_bps: params.partner_fee_bps,
+        partner_fee_paid: 0,
+        bump,
+        cancel_by: StateChangeAuth::from_flag(params.cancel_by),
+        pause_by: StateChangeAuth::from_flag(params.pause_by),
+        resume_by: StateChangeAuth::from_flag(params.resume_by),
+        withdraw_by: StateChangeAuth::from_flag(params.withdraw_by),
+        edit_by: StateChangeAuth::from_flag(params.edit_by),
+        is_cliff_percent: params.is_cliff_percent,
+        run_state: RunState::Running,
+        is_infinite: params.is_infinite,
+        is_split: false,
+        schedule: match params.is_linear {
+            true => ScheduleKind::Linear,
+            false => ScheduleKind::Interval,
+        },
+        checkpoints: Vec::new(),
+    })
+}
+
+/// Validates a stream unlocking along `checkpoints` and builds its account state.
+///
+/// The interval, rate, duration and cliff of `params` are not used, the stream starts at
+/// its start time and ends at the last checkpoint, which must unlock the whole amount.
+pub fn build_step_stream_account(
+    params: &CreateStreamParams,
+    checkpoints: Vec<Checkpoint>,
+    sender: Pubkey,
+    recipient: Pubkey,
+    token_address: Pubkey,
+    bump: u8,
+    timestamp: u64,
+) -> Result<StreamAccount> {
+    let start = check_stream_params(params, sender, recipient, timestamp)?;
+
+    // Step schedules have a fixed end, they cannot be reloaded
+    require!(!params.is_infinite, MyError::InvalidCheckpoints);
+    require!(
+        !checkpoints.is_empty() && checkpoints.len() <= StreamAccount::MAX_CHECKPOINTS,
+        MyError::InvalidCheckpoints
+    );
+
+    let mut previous = Checkpoint {
+        timestamp: start,
+        cumulative_unlocked: 0,
+    };
+    for (i, checkpoint) in checkpoints.iter().enumerate() {
+        // Only the first checkpoint may unlock right at the start
+        require!(
+            checkpoint.timestamp > previous.timestamp || (i == 0 && checkpoint.timestamp == start),
+            MyError::InvalidCheckpoints
+        );
+        require!(
+            checkpoint.cumulative_unlocked > previous.cumulative_unlocked,
+            MyError::InvalidCheckpoints
+        );
+        previous = *checkpoint;
+    }
+    require!(
+        previous.cumulative_unlocked == params.amount,
+        MyError::InvalidCheckpoints
+    );
+
+    Ok(StreamAccount {
+        stream_id: params.stream_id.clone(),
+        stream_title: params.stream_title.clone(),
+        recipient,
+        sender,
+        token_address,
+        create_time: timestamp,
+        start_time: start,
+        stop_time: previous.timestamp,
+        remaining_balance: params.amount,
+        deposit: params.amount,
+        withdrawn: 0,
+        cliff_amount: 0,
+        cliff_time: start,
+        cliff_time_left: 0,
+        interval: 0,
+        rate_of_stream: 0,
+        time_left: 0,
+        paused_amount: 0,
+        protocol_fee_paid: 0,
+        partner: params.partner.unwrap_or_default(),
+        partner_fee_bps: params.partner_fee_bps,
+        partner_fee_paid: 0,
+        bump,
+        cancel_by: StateChangeAuth::from_flag(params.cancel_by),
+        pause_by: StateChangeAuth::from_flag(params.pause_by),
+        resume_by: StateChangeAuth::from_flag(params.resume_by),
+        withdraw_by: StateChangeAuth::from_flag(params.withdraw_by),
+        edit_by: StateChangeAuth::from_flag(params.edit_by),
+        is_cliff_percent: false,
+        run_state: RunState::Running,
+        is_infinite: false,
+        is_split: false,
+        schedule: ScheduleKind::Steps,
+        checkpoints,
+    })
+}
+
+/// Checks shared by every kind of schedule, returns the start time of the stream.
+fn check_stream_params(
+    params: &CreateStreamParams,
+    sender: Pubkey,
+    recipient: Pubkey,
+    timestamp: u64,
+) -> Result<u64> {
+    let start = match params.start_now {
+        true => timestamp,
+        false => params.start,
+    };
+
+    // Stream Title shouldn't be longer than 50 characters
+    require!(params.stream_title.len() <= 50, MyError::TitleTooLong);
+
+    // Recipient shouldn't be same as Sender
+    require!(recipient != sender, MyError::SenderIsRecipient);
+
+    // Amount to Stream should be greater than 0
+    require!(params.amount > 0, MyError::DepositIsZero);
+
+    // Start time of Stream should be in future
+    require!(start >= timestamp, MyError::PastStartTime);
+
+    require!(params.cancel_by <= 3, MyError::InvalidCancelBy);
+    require!(params.pause_by <= 3, MyError::InvalidPauseBy);
+    require!(params.withdraw_by <= 2, MyError::InvalidWithdrawBy);
+    require!(params.resume_by <= 3, MyError::InvalidResumeBy);
+    require!(params.edit_by <= 2, MyError::InvalidEditBy);
+
+    // A partner fee needs a partner and stays under the cap
+    require!(
+        params.partner.is_some() || params.partner_fee_bps == 0,
+        MyError::IncorrectPartner
+    );
+    require!(
+        params.partner_fee_bps <= StreamAccount::MAX_PARTNER_FEE_BPS,
+        MyError::FeeTooHigh
+    );
+
+    Ok(start)
+}
+
+/// Validates the recipients of a split stream and builds its split account.
+pub fn build_split_account(
+    stream: Pubkey,
+    sender: Pubkey,
+    recipients: &[SplitRecipient],
+    bump: u8,
+) -> Result<SplitAccount> {
+    require!(
+        recipients.len() >= SplitAccount::MIN_SHARES
+            && recipients.len() <= SplitAccount::MAX_SHARES,
+        MyError::InvalidSplit
+    );
+
+    let mut total: u64 = 0;
+    for (i, share) in recipients.iter().enumerate() {
+        require!(share.weight_bps > 0, MyError::InvalidSplit);
+        require!(share.recipient != sender, MyError::SenderIsRecipient);
+        require!(
+            recipients[..i]
+                .iter()
+                .all(|s| s.recipient != share.recipient),
+            MyError::InvalidSplit
+        );
+        total = total
+            .checked_add(share.weight_bps)
+            .ok_or(MyError::MathOverflow)?;
+    }
+    require!(total == MAX_BPS, MyError::InvalidSplit);
+
+    Ok(SplitAccount {
+        stream,
+        shares: recipients
+            .iter()
+            .map(|s| SplitShare {
+                recipient: s.recipient,
+                weight_bps: s.weight_bps,
+                withdrawn: 0,
+            })
+            .collect(),
+        bump,
+    })
+}
+
+/// Accounts credited when the stream pays its recipient.
+///
+/// For token streams these are token accounts, for native streams the wallets themselves.
+/// The treasury is left out when the protocol charges no fee, the partner when the stream
+/// pays no partner fee.
+pub struct Payees<'info> {
+    pub recipient: AccountInfo<'info>,
+    pub treasury: Option<AccountInfo<'info>>,
+    pub partner: Option<AccountInfo<'info>>,
+}
+
+/// How an amount paid out of a stream was split.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Payout {
+    // Total taken out of the stream, counted as withdrawn
+    pub amount: u64,
+    pub protocol_fee: u64,
+    pub partner_fee: u64,
+    // Withheld by the mint's transfer fee on the way out, not received by anyone
+    pub transfer_fee: u64,
+}
+
+impl Payout {
+    // Part of the amount received by the recipient
+    pub fn net(&self) -> u64 {
+        self.amount - self.protocol_fee - self.partner_fee
+    }
+}
+
+pub fn now() -> Result<u64> {
+    Ok(Clock::get()?.unix_timestamp as u64)
+}
+
+/// Creates the rent-exempt account of the PDA signed for by `seeds`, owned by `owner`.
+///
+/// Like Anchor's `init`, lamports sent to the address beforehand do not block it: such an
+/// account is topped up to the rent-exempt minimum, then allocated and assigned.
+pub fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    space: usize,
+    owner: &Pubkey,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer = &[seeds];
+
+    if account.lamports() == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                signer,
+            ),
+            rent,
+            space as u64,
+            owner,
+        );
+    }
+
+    let top_up = rent.saturating_sub(account.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            signer,
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            signer,
+        ),
+        owner,
+    )
+}
+
+/// Deposits the initial balance of a stream built by `build_stream_account`.
+///
+/// The balance of the stream is what actually reached the vault.
+pub fn open<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    config: &ProgramConfig,
+    vault: &AssetVault<'info>,
+    from: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+) -> Result<()> {
+    if !stream.is_native() {
+        require!(
+            config.is_mint_allowed(&stream.token_address),
+            MyError::MintNotAllowed
+        );
+    }
+
+    let received = vault.deposit(from, authority, stream.deposit)?;
+    stream.deposit = received;
+    stream.remaining_balance = received;
+
+    emit!(StreamCreated {
+        stream: stream.key(),
+        sender: stream.sender,
+        recipient: stream.recipient,
+        token_address: stream.token_address,
+        partner: stream.partner,
+        partner_fee_bps: stream.partner_fee_bps,
+        deposit: stream.deposit,
+        cliff_amount: stream.cliff_amount,
+        cliff_time: stream.cliff_time,
+        start_time: stream.start_time,
+        stop_time: stream.stop_time,
+        interval: stream.interval,
+        rate_of_stream: stream.rate_of_stream,
+        timestamp: now()?,
+    });
+
+    Ok(())
+}
+
+/// Pays `amount` out of the stream, taking the protocol and partner fees out of it.
+fn pay_recipient<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    config: &ProgramConfig,
+    vault: &AssetVault<'info>,
+    payees: &Payees<'info>,
+    amount: u64,
+) -> Result<Payout> {
+    let mut payout = Payout {
+        amount,
+        protocol_fee: config.protocol_fee(amount),
+        partner_fee: stream.partner_fee(amount),
+        transfer_fee: 0,
+    };
+
+    if payout.protocol_fee > 0 {
+        let treasury = payees.treasury.as_ref().ok_or(MyError::InvalidTreasury)?;
+        require!(
+            treasury.key() == vault.destination_of(&config.treasury),
+            MyError::InvalidTreasury
+        );
+        // A fee too small to open a new wallet is waived rather than failing the payout
+        if !vault.can_receive(treasury, payout.protocol_fee)? {
+            payout.protocol_fee = 0;
+        }
+    }
+    if payout.partner_fee > 0 {
+        let partner = payees.partner.as_ref().ok_or(MyError::IncorrectPartner)?;
+        require!(
+            partner.key() == vault.destination_of(&stream.partner),
+            MyError::IncorrectPartner
+        );
+        if !vault.can_receive(partner, payout.partner_fee)? {
+            payout.partner_fee = 0;
+        }
+    }
+
+    payout.transfer_fee = [
+        vault.pay(&payees.recipient, payout.net())?,
+        match &payees.treasury {
+            Some(treasury) => vault.pay(treasury, payout.protocol_fee)?,
+            None => 0,
+        },
+        match &payees.partner {
+            Some(partner) => vault.pay(partner, payout.partner_fee)?,
+            None => 0,
+        },
+    ]
+    .iter()
+    .sum();
+
+    stream.remaining_balance = stream
+        .remaining_balance
+        .checked_sub(amount)
+        .ok_or(MyError::BalanceUnderflow)?;
+    stream.withdrawn = stream
+        .withdrawn
+        .checked_add(amount)
+        .ok_or(MyError::MathOverflow)?;
+    stream.protocol_fee_paid = stream
+        .protocol_fee_paid
+        .checked_add(payout.protocol_fee)
+        .ok_or(MyError::MathOverflow)?;
+    stream.partner_fee_paid = stream
+        .partner_fee_paid
+        .checked_add(payout.partner_fee)
+        .ok_or(MyError::MathOverflow)?;
+
+    Ok(payout)
+}
+
+fn emit_withdrawn(
+    stream: &Account<StreamAccount>,
+    actor: Pubkey,
+    recipient: Pubkey,
+    destination: Pubkey,
+    payout: &Payout,
+    timestamp: u64,
+) {
+    emit!(Withdrawn {
+        stream: stream.key(),
+        actor,
+        recipient,
+        destination,
+        amount: payout.amount,
+        protocol_fee: payout.protocol_fee,
+        partner_fee: payout.partner_fee,
+        transfer_fee: payout.transfer_fee,
+        remaining_balance: stream.remaining_balance,
+        withdrawn: stream.withdrawn,
+        timestamp,
+    });
+}
+
+/// Withdraws everything accrued, or `amount` of it, to the recipient's payee account.
+pub fn withdraw<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    config: &ProgramConfig,
+    vault: &AssetVault<'info>,
+    payees: &Payees<'info>,
+    actor: Pubkey,
+    amount: Option<u64>,
+) -> Result<Payout> {
+    let timestamp = now()?;
+
+    require!(!stream.is_split, MyError::SplitStream);
+    require!(
+        stream.is_allowed(&stream.withdraw_by, &actor),
+        MyError::NotAuthorized
+    );
+    stream.transition(StreamAction::Withdraw, timestamp)?;
+    require!(timestamp >= stream.cliff_time, MyError::CliffNotReached);
+
+    let ready_for_withdrawal = stream.withdrawable_at(timestamp)?;
+    require!(ready_for_withdrawal > 0, MyError::NothingToWithdraw);
+
+    // Withdraw everything accrued unless a smaller amount is requested
+    let amt = match amount {
+        Some(amount) => {
+            require!(amount > 0, MyError::AmountIsZero);
+            require!(
+                amount <= ready_for_withdrawal,
+                MyError::AmountExceedsWithdrawable
+            );
+            amount
+        }
+        None => ready_for_withdrawal,
+    };
+
+    let payout = pay_recipient(stream, config, vault, payees, amt)?;
+    emit_withdrawn(
+        stream,
+        actor,
+        stream.recipient,
+        payees.recipient.key(),
+        &payout,
+        timestamp,
+    );
+
+    Ok(payout)
+}
+
+/// Like `withdraw`, but a stream with nothing to withdraw right now is skipped, not failed.
+pub fn withdraw_if_accrued<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    config: &ProgramConfig,
+    vault: &AssetVault<'info>,
+    payees: &Payees<'info>,
+    actor: Pubkey,
+) -> Result<Payout> {
+    let timestamp = now()?;
+
+    require!(!stream.is_split, MyError::SplitStream);
+    require!(
+        stream.is_allowed(&stream.withdraw_by, &actor),
+        MyError::NotAuthorized
+    );
+
+    if stream
+        .transition(StreamAction::Withdraw, timestamp)
+        .is_err()
+        || timestamp < stream.cliff_time
+    {
+        return Ok(Payout::default());
+    }
+
+    let ready_for_withdrawal = stream.withdrawable_at(timestamp)?;
+    if ready_for_withdrawal == 0 {
+        return Ok(Payout::default());
+    }
+
+    let payout = pay_recipient(stream, config, vault, payees, ready_for_withdrawal)?;
+    emit_withdrawn(
+        stream,
+        actor,
+        stream.recipient,
+        payees.recipient.key(),
+        &payout,
+        timestamp,
+    );
+
+    Ok(payout)
+}
+
+/// Pays the recipient what has vested and refunds the rest of the balance to `sender`.
+pub fn cancel<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    config: &ProgramConfig,
+    vault: &AssetVault<'info>,
+    payees: &Payees<'info>,
+    sender: &AccountInfo<'info>,
+    actor: Pubkey,
+) -> Result<()> {
+    let timestamp = now()?;
+
+    require!(
+        stream.is_allowed(&stream.cancel_by, &actor),
+        MyError::NotAuthorized
+    );
+    let cancelled = stream.transition(StreamAction::Cancel, timestamp)?;
+
+    if stream.is_split {
+        return cancel_split(stream, vault, sender, actor, timestamp);
+    }
+
+    let owed = stream.owed_on_cancel_at(timestamp)?;
+    let payout = pay_recipient(stream, config, vault, payees, owed)?;
+
+    let refund = stream.remaining_balance;
+    let refund_fee = vault.pay(sender, refund)?;
+    stream.remaining_balance = 0;
+    stream.set_status(cancelled);
+
+    emit!(Cancelled {
+        stream: stream.key(),
+        actor,
+        recipient_amount: payout.amount,
+        sender_amount: refund,
+        protocol_fee: payout.protocol_fee,
+        partner_fee: payout.partner_fee,
+        transfer_fee: payout
+            .transfer_fee
+            .checked_add(refund_fee)
+            .ok_or(MyError::MathOverflow)?,
+        withdrawn: stream.withdrawn,
+        remaining_balance: stream.remaining_balance,
+        timestamp,
+    });
+
+    Ok(())
+}
+
+/// Refunds the unvested balance of a split stream to `sender`.
+///
+/// What has vested stays in the vault, where every recipient withdraws their share of it.
+fn cancel_split<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    vault: &AssetVault<'info>,
+    sender: &AccountInfo<'info>,
+    actor: Pubkey,
+    timestamp: u64,
+) -> Result<()> {
+    let vested_unpaid = stream.owed_on_cancel_at(timestamp)?;
+
+    let refund = stream
+        .remaining_balance
+        .checked_sub(vested_unpaid)
+        .ok_or(MyError::BalanceUnderflow)?;
+    let refund_fee = vault.pay(sender, refund)?;
+    stream.remaining_balance = vested_unpaid;
+    stream.set_status(StreamStatus::Cancelled);
+
+    emit!(Cancelled {
+        stream: stream.key(),
+        actor,
+        recipient_amount: vested_unpaid,
+        sender_amount: refund,
+        protocol_fee: 0,
+        partner_fee: 0,
+        transfer_fee: refund_fee,
+        withdrawn: stream.withdrawn,
+        remaining_balance: stream.remaining_balance,
+        timestamp,
+    });
+
+    Ok(())
+}
+
+/// Withdraws everything owed to `recipient` under a split stream.
+///
+/// The recipient is owed their weight of everything vested so far, less what they have
+/// withdrawn before. After a cancel that is their weight of what vested until then.
+pub fn withdraw_share<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    split: &mut SplitAccount,
+    config: &ProgramConfig,
+    vault: &AssetVault<'info>,
+    payees: &Payees<'info>,
+    recipient: Pubkey,
+) -> Result<Payout> {
+    let timestamp = now()?;
+
+    require!(stream.is_split, MyError::SplitStream);
+    let index = split.position(&recipient).ok_or(MyError::NotInSplit)?;
+
+    let is_cancelled =
+        stream.transition(StreamAction::Withdraw, timestamp)? == StreamStatus::Cancelled;
+    // A reload vests more after the stop_time of an infinite stream, so only its cancel is final
+    let is_final = is_cancelled || (timestamp >= stream.stop_time && !stream.is_infinite);
+    if !is_cancelled {
+        require!(timestamp >= stream.cliff_time, MyError::CliffNotReached);
+    }
+
+    let unpaid = match is_cancelled {
+        true => stream.remaining_balance,
+        false => stream.withdrawable_at(timestamp)?,
+    };
+    let vested = stream
+        .withdrawn
+        .checked_add(unpaid)
+        .ok_or(MyError::MathOverflow)?;
+
+    let amount = split
+        .entitlement(index, vested, is_final)
+        .saturating_sub(split.shares[index].withdrawn);
+    require!(amount > 0, MyError::NothingToWithdraw);
+
+    let payout = pay_recipient(stream, config, vault, payees, amount)?;
+    let share = &mut split.shares[index];
+    share.withdrawn = share
+        .withdrawn
+        .checked_add(amount)
+        .ok_or(MyError::MathOverflow)?;
+
+    emit_withdrawn(
+        stream,
+        recipient,
+        recipient,
+        payees.recipient.key(),
+        &payout,
+        timestamp,
+    );
+
+    Ok(payout)
+}
+
+/// Pays out what has accrued so far and records it as vested, so that the schedule can
+/// be stopped or replaced without losing it. Only for an active stream.
+fn settle<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    config: &ProgramConfig,
+    vault: &AssetVault<'info>,
+    payees: &Payees<'info>,
+    timestamp: u64,
+) -> Result<Payout> {
+    let ready_for_withdrawal = stream.withdrawable_at(timestamp)?;
+
+    // Before the cliff date the accrued amount stays locked in the stream, so does the
+    // accrued amount of a split stream until each recipient withdraws their share
+    let mut recipient_balance = ready_for_withdrawal;
+    if timestamp < stream.cliff_time || stream.is_split {
+        recipient_balance = 0;
+    }
+    let locked_balance = ready_for_withdrawal
+        .checked_sub(recipient_balance)
+        .ok_or(MyError::BalanceUnderflow)?;
+
+    let payout = pay_recipient(stream, config, vault, payees, recipient_balance)?;
+
+    stream.paused_amount = stream
+        .withdrawn
+        .checked_add(locked_balance)
+        .ok_or(MyError::MathOverflow)?;
+
+    Ok(payout)
+}
+
+pub fn pause<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    config: &ProgramConfig,
+    vault: &AssetVault<'info>,
+    payees: &Payees<'info>,
+    actor: Pubkey,
+) -> Result<()> {
+    let timestamp = now()?;
+
+    require!(
+        stream.is_allowed(&stream.pause_by, &actor),
+        MyError::NotAuthorized
+    );
+    let paused = stream.transition(StreamAction::Pause, timestamp)?;
+
+    let payout = settle(stream, config, vault, payees, timestamp)?;
+
+    stream.set_status(paused);
+    stream.time_left = stream
+        .stop_time
+        .checked_sub(timestamp)
+        .ok_or(MyError::MathOverflow)?;
+    stream.cliff_time_left = stream.cliff_time.saturating_sub(timestamp);
+
+    emit!(Paused {
+        stream: stream.key(),
+        actor,
+        amount: payout.amount,
+        protocol_fee: payout.protocol_fee,
+        partner_fee: payout.partner_fee,
+        transfer_fee: payout.transfer_fee,
+        remaining_balance: stream.remaining_balance,
+        withdrawn: stream.withdrawn,
+        time_left: stream.time_left,
+        timestamp,
+    });
+
+    Ok(())
+}
+
+pub fn resume(stream: &mut Account<StreamAccount>, actor: Pubkey) -> Result<()> {
+    let timestamp = now()?;
+
+    require!(
+        stream.is_allowed(&stream.resume_by, &actor),
+        MyError::NotAuthorized
+    );
+    let resumed = stream.transition(StreamAction::Resume, timestamp)?;
+
+    let stop = timestamp
+        .checked_add(stream.time_left)
+        .ok_or(MyError::MathOverflow)?;
+
+    // Checkpoints still ahead move back by the time spent paused
+    if stream.schedule == ScheduleKind::Steps {
+        let paused_at = stream
+            .stop_time
+            .checked_sub(stream.time_left)
+            .ok_or(MyError::BalanceUnderflow)?;
+        let paused_for = timestamp
+            .checked_sub(paused_at)
+            .ok_or(MyError::BalanceUnderflow)?;
+        for checkpoint in stream
+            .checkpoints
+            .iter_mut()
+            .filter(|c| c.timestamp > paused_at)
+        {
+            checkpoint.timestamp = checkpoint
+                .timestamp
+                .checked_add(paused_for)
+                .ok_or(MyError::MathOverflow)?;
+        }
+    }
+
+    stream.start_time = timestamp;
+    stream.stop_time = stop;
+    stream.cliff_time = timestamp
+        .checked_add(stream.cliff_time_left)
+        .ok_or(MyError::MathOverflow)?;
+    stream.cliff_time_left = 0;
+    stream.set_status(resumed);
+
+    emit!(Resumed {
+        stream: stream.key(),
+        actor,
+        start_time: stream.start_time,
+        stop_time: stream.stop_time,
+        cliff_time: stream.cliff_time,
+        timestamp,
+    });
+
+    Ok(())
+}
+
+/// Settles the stream if it is running and streams the unvested balance on a new schedule.
+#[allow(clippy::too_many_arguments)]
+pub fn edit<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    config: &ProgramConfig,
+    vault: &AssetVault<'info>,
+    payees: &Payees<'info>,
+    actor: Pubkey,
+    stream_title: String,
+    interval: u64,
+    rate: u64,
+    duration: u64,
+) -> Result<()> {
+    let timestamp = now()?;
+
+    require!(
+        stream.is_allowed(&stream.edit_by, &actor),
+        MyError::NotAuthorized
+    );
+    let status = stream.transition(StreamAction::Edit, timestamp)?;
+    require!(
+        stream.schedule == ScheduleKind::Interval,
+        MyError::ScheduleNotEditable
+    );
+
+    // Stream Title shouldn't be longer than 50 characters
+    require!(stream_title.len() <= 50, MyError::TitleTooLong);
+
+    // Interval of Stream should be greater than 0
+    require!(interval > 0, MyError::IntervalIsZero);
+
+    let is_running = status == StreamStatus::Active;
+    let mut settled = Payout::default();
+
+    if is_running {
+        // Settle everything accrued under the old schedule before replacing it
+        settled = settle(stream, config, vault, payees, timestamp)?;
+    }
+
+    // Whatever has vested but is still held by the stream is not rescheduled
+    let vested = match stream.paused_amount {
+        0 => stream.cliff_amount,
+        _ => stream.paused_amount,
+    };
+    let vested_unpaid = vested
+        .checked_sub(stream.withdrawn)
+        .ok_or(MyError::BalanceUnderflow)?;
+    let amount = stream
+        .remaining_balance
+        .checked_sub(vested_unpaid)
+        .ok_or(MyError::BalanceUnderflow)?;
+
+    // Balance left to Stream should be greater than the Rate of Stream
+    require!(amount >= rate, MyError::DepositSmallerThanTime);
+
+    // Stop Time has to come after the Start Time
+    require!(duration > 0, MyError::FutureStartTime);
+
+    // The new schedule stops the moment everything has vested
+    require!(
+        interval_duration(amount, interval, rate) == Some(duration),
+        MyError::IncorrectDuration
+    );
+
+    if status == StreamStatus::Paused {
+        require!(
+            stream.cliff_time_left <= duration,
+            MyError::InvalidCliffTime
+        );
+        stream.time_left = duration;
+    } else {
+        let new_start = match is_running {
+            true => timestamp,
+            false => stream.start_time,
+        };
+        let new_stop = new_start
+            .checked_add(duration)
+            .ok_or(MyError::MathOverflow)?;
+        require!(stream.cliff_time <= new_stop, MyError::InvalidCliffTime);
+        stream.start_time = new_start;
+        stream.stop_time = new_stop;
+    }
+
+    stream.stream_title = stream_title;
+    stream.interval = interval;
+    stream.rate_of_stream = rate;
+
+    emit!(Edited {
+        stream: stream.key(),
+        actor,
+        settled: settled.amount,
+        protocol_fee: settled.protocol_fee,
+        partner_fee: settled.partner_fee,
+        transfer_fee: settled.transfer_fee,
+        remaining_balance: stream.remaining_balance,
+        withdrawn: stream.withdrawn,
+        interval,
+        rate_of_stream: rate,
+        start_time: stream.start_time,
+        stop_time: stream.stop_time,
+        time_left: stream.time_left,
+        timestamp,
+    });
+
+    Ok(())
+}
+
+/// Tops up an infinite stream and extends it by the time `amount` lasts at its rate.
+pub fn reload<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    vault: &AssetVault<'info>,
+    from: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let timestamp = now()?;
+    let status = stream.transition(StreamAction::Reload, timestamp)?;
+    require!(stream.is_infinite, MyError::NotInfiniteStream);
+    require!(amount > 0, MyError::DepositIsZero);
+
+    let rate = stream.rate_of_stream;
+    let interval = stream.interval;
+
+    // The stream is extended by what actually reached the vault
+    let amount = vault.deposit(from, authority, amount)?;
+
+    /* Without this, the duration would be zero. */
+    require!(amount >= rate, MyError::DepositSmallerThanTime);
+
+    let duration = mul_div_round(amount, interval, rate).ok_or(MyError::IncorrectDuration)?;
+
+    match status {
+        StreamStatus::Paused => {
+            stream.time_left = stream
+                .time_left
+                .checked_add(duration)
+                .ok_or(MyError::MathOverflow)?;
+        }
+        // Everything deposited so far has vested, the top-up streams from now on instead of
+        // from the old stop_time, which would make it withdrawable at once
+        StreamStatus::Depleted => {
+            stream.paused_amount = stream
+                .withdrawn
+                .checked_add(stream.remaining_balance)
+                .ok_or(MyError::MathOverflow)?;
+            stream.start_time = timestamp;
+            stream.stop_time = timestamp
+                .checked_add(duration)
+                .ok_or(MyError::MathOverflow)?;
+        }
+        _ => {
+            // A linear stream spreads its balance over the time left, so what has vested so
+            // far is recorded first and the top-up only vests from now on
+            if stream.schedule == ScheduleKind::Linear && timestamp > stream.start_time {
+                stream.paused_amount = stream
+                    .withdrawn
+                    .checked_add(stream.withdrawable_at(timestamp)?)
+                    .ok_or(MyError::MathOverflow)?;
+                stream.start_time = timestamp;
+            }
+            stream.stop_time = stream
+                .stop_time
+                .checked_add(duration)
+                .ok_or(MyError::MathOverflow)?;
+        }
+    }
+
+    stream.remaining_balance = stream
+        .remaining_balance
+        .checked_add(amount)
+        .ok_or(MyError::MathOverflow)?;
+    stream.deposit = stream
+        .deposit
+        .checked_add(amount)
+        .ok_or(MyError::MathOverflow)?;
+
+    emit!(Reloaded {
+        stream: stream.key(),
+        actor: authority.key(),
+        amount,
+        deposit: stream.deposit,
+        remaining_balance: stream.remaining_balance,
+        stop_time: stream.stop_time,
+        time_left: stream.time_left,
+        timestamp,
+    });
+
+    Ok(())
+}
+
+/// Closes an emptied stream and returns its rent to the sender.
+pub fn delete<'info>(
+    stream: &mut Account<'info, StreamAccount>,
+    sender: &AccountInfo<'info>,
+) -> Result<()> {
+    let timestamp = now()?;
+
+    stream.transition(StreamAction::Delete, timestamp)?;
+    require!(stream.sender == sender.key(), MyError::NotAuthorized);
+
+    stream.close(sender.clone())?;
+
+    emit!(Deleted {
+        stream: stream.key(),
+        actor: sender.key(),
+        timestamp,
+    });
+
+    Ok(())
+}
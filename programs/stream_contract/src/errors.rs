@@ -0,0 +1,108 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum MyError {
+    #[msg("Recipient cannot be same as Sender.")]
+    SenderIsRecipient,
+    #[msg("Deposit Amount is Zero")]
+    DepositIsZero,
+    #[msg("Interval of Streaming is Zero")]
+    IntervalIsZero,
+    #[msg("Amount is Zero")]
+    AmountIsZero,
+    #[msg("Start Time is before Block Timestamp.")]
+    PastStartTime,
+    #[msg("Stop Time must be after the Start Time.")]
+    FutureStartTime,
+    #[msg("Deposit is smaller than the Time Delta")]
+    DepositSmallerThanTime,
+    // No longer raised, kept so that the codes after it stay the same
+    #[msg("Deposit is not a Multiple of the Time Delta")]
+    DepositNotMultipleOfTime,
+    #[msg("The Stream ID is Incorrect")]
+    IncorrectStreamId,
+    #[msg("Stream Title cannot be longer than 50 characters.")]
+    TitleTooLong,
+    #[msg("Nothing To Withdraw as of now.")]
+    NothingToWithdraw,
+    #[msg("Incorrect Recipient Address")]
+    IncorrectRecipient,
+    #[msg("Incorrect Sender Address")]
+    IncorrectSender,
+    #[msg("Incorrect Token Address")]
+    IncorrectTokenAddress,
+    #[msg("Stream has not started yet.")]
+    StreamNotStarted,
+    #[msg("Stream is Paused. Resume the Stream to Withdraw.")]
+    StreamIsPaused,
+    #[msg("Stream is Already Paused.")]
+    StreamAlreadyPaused,
+    #[msg("Stream is not Paused.")]
+    StreamNotPaused,
+    #[msg("Stream has already Ended.")]
+    StreamAlreadyEnded,
+    #[msg("Stream is already Cancelled.")]
+    StreamAlreadyCancelled,
+    #[msg("Stream Not Empty. Withdraw Tokens completely and then try again.")]
+    StreamNotEmpty,
+    #[msg("This is not an infinite stream.")]
+    NotInfiniteStream,
+    #[msg("You are not Authorized to perform the desired operation. !!")]
+    NotAuthorized,
+    #[msg("Invalid Value for Cancel By Flag")]
+    InvalidCancelBy,
+    #[msg("Invalid Value for Pause By Flag")]
+    InvalidPauseBy,
+    #[msg("Invalid Value for Withdraw By Flag")]
+    InvalidWithdrawBy,
+    #[msg("Invalid Value for Resume By Flag")]
+    InvalidResumeBy,
+    #[msg("Invalid Value for Edit By Flag")]
+    InvalidEditBy,
+    #[msg("The Duration is incorrect. Please check the values of Amount, Rate, Interval and Duration.")]
+    IncorrectDuration,
+    #[msg("The Associated Token Account of Recipient is Incorrect.")]
+    AssociatedTokenAccountIncorrect,
+    #[msg("Cliff Percentage cannot be more than 10000 basis points.")]
+    InvalidCliffPercent,
+    #[msg("Cliff Time must be between the Start Time and the Stop Time.")]
+    InvalidCliffTime,
+    #[msg("Cliff Time has not been reached yet.")]
+    CliffNotReached,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+    #[msg("Balance is smaller than the amount being moved.")]
+    BalanceUnderflow,
+    #[msg("Requested Amount is more than the Withdrawable Amount.")]
+    AmountExceedsWithdrawable,
+    #[msg("Destination Account is not valid for this Stream.")]
+    InvalidDestination,
+    #[msg("Batch is empty or its Accounts do not match its Streams.")]
+    InvalidBatch,
+    #[msg("Program is halted by the admin.")]
+    ProgramHalted,
+    #[msg("This Token is not allowed for Streams.")]
+    MintNotAllowed,
+    #[msg("Too many allowed Mints.")]
+    TooManyMints,
+    #[msg("Protocol Fee is above the maximum.")]
+    FeeTooHigh,
+    #[msg("Treasury Account does not match the config.")]
+    InvalidTreasury,
+    #[msg("Partner Account does not match the Stream.")]
+    IncorrectPartner,
+    #[msg("Split needs 2 to 20 distinct Recipients with Weights adding up to 10000 basis points.")]
+    InvalidSplit,
+    #[msg("Signer is not a Recipient of this Split.")]
+    NotInSplit,
+    #[msg("Split Streams are withdrawn through the Split instructions.")]
+    SplitStream,
+    #[msg("Checkpoints must start after the Start Time and increase until they unlock the whole Deposit.")]
+    InvalidCheckpoints,
+    #[msg("Only Streams with an Interval Schedule can be edited.")]
+    ScheduleNotEditable,
+    #[msg("Stream cannot do this in its current Status.")]
+    InvalidStateTransition,
+    #[msg("Cliff Amount cannot be more than the Deposit.")]
+    CliffExceedsDeposit,
+}
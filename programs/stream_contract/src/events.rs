@@ -0,0 +1,120 @@
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct StreamCreated {
+    pub stream: Pubkey,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    // Default for native streams
+    pub token_address: Pubkey,
+    // Default when the stream has no partner
+    pub partner: Pubkey,
+    pub partner_fee_bps: u64,
+    pub deposit: u64,
+    pub cliff_amount: u64,
+    pub cliff_time: u64,
+    pub start_time: u64,
+    pub stop_time: u64,
+    pub interval: u64,
+    pub rate_of_stream: u64,
+    pub timestamp: u64,
+}
+
+#[event]
+pub struct Withdrawn {
+    pub stream: Pubkey,
+    pub actor: Pubkey,
+    pub recipient: Pubkey,
+    // Account credited, the recipient or its token account unless chosen otherwise
+    pub destination: Pubkey,
+    pub amount: u64,
+    // Part of amount paid to the treasury
+    pub protocol_fee: u64,
+    // Part of amount paid to the partner
+    pub partner_fee: u64,
+    // Withheld by the mint's transfer fee from the payments, part of amount
+    pub transfer_fee: u64,
+    pub remaining_balance: u64,
+    pub withdrawn: u64,
+    pub timestamp: u64,
+}
+
+#[event]
+pub struct Paused {
+    pub stream: Pubkey,
+    pub actor: Pubkey,
+    // Accrued amount paid to the recipient when pausing
+    pub amount: u64,
+    pub protocol_fee: u64,
+    pub partner_fee: u64,
+    pub transfer_fee: u64,
+    pub remaining_balance: u64,
+    pub withdrawn: u64,
+    pub time_left: u64,
+    pub timestamp: u64,
+}
+
+#[event]
+pub struct Resumed {
+    pub stream: Pubkey,
+    pub actor: Pubkey,
+    pub start_time: u64,
+    pub stop_time: u64,
+    pub cliff_time: u64,
+    pub timestamp: u64,
+}
+
+#[event]
+pub struct Edited {
+    pub stream: Pubkey,
+    pub actor: Pubkey,
+    // Accrued amount paid to the recipient under the old schedule
+    pub settled: u64,
+    pub protocol_fee: u64,
+    pub partner_fee: u64,
+    pub transfer_fee: u64,
+    pub remaining_balance: u64,
+    pub withdrawn: u64,
+    pub interval: u64,
+    pub rate_of_stream: u64,
+    pub start_time: u64,
+    pub stop_time: u64,
+    pub time_left: u64,
+    pub timestamp: u64,
+}
+
+#[event]
+pub struct Cancelled {
+    pub stream: Pubkey,
+    pub actor: Pubkey,
+    pub recipient_amount: u64,
+    pub sender_amount: u64,
+    // Part of recipient_amount paid to the treasury
+    pub protocol_fee: u64,
+    // Part of recipient_amount paid to the partner
+    pub partner_fee: u64,
+    // Withheld by the mint's transfer fee from both payments
+    pub transfer_fee: u64,
+    pub withdrawn: u64,
+    pub remaining_balance: u64,
+    pub timestamp: u64,
+}
+
+#[event]
+pub struct Reloaded {
+    pub stream: Pubkey,
+    pub actor: Pubkey,
+    pub amount: u64,
+    pub deposit: u64,
+    pub remaining_balance: u64,
+    pub stop_time: u64,
+    pub time_left: u64,
+    pub timestamp: u64,
+}
+
+#[event]
+pub struct Deleted {
+    pub stream: Pubkey,
+    pub actor: Pubkey,
+    pub timestamp: u64,
+}
@@ -1,1435 +1,841 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::account_info::AccountInfo;
-use anchor_lang::AccountsClose;
 use std::string::*;
 
-use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
-use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::program::set_return_data;
+use anchor_spl::associated_token::{create_idempotent, Create};
+use anchor_spl::token_interface::TokenAccount;
 
-const BLANK: &str = "                                ";
+pub mod contexts;
+pub mod engine;
+pub mod errors;
+pub mod events;
+pub mod schedule;
+pub mod state;
+pub mod status;
+pub mod vault;
 
-declare_id!("F6ZLaARn1TvVHh15hSeymSh6r9XhbiFa5bLiceHWb87d");
-
-#[program]
-pub mod stream_contract {
+pub use contexts::*;
+pub use errors::MyError;
+pub use events::*;
+pub use schedule::{interval_duration, mul_div_round};
+pub use state::*;
+pub use status::{StreamAction, StreamStatus};
 
-    use super::*;
+use engine::{build_split_account, build_step_stream_account, build_stream_account, Payees};
+use vault::AssetVault;
 
-    pub fn create_stream(
-        ctx: Context<CreateStream>,
-        stream_id: String,
-        stream_title: String,
-        bump: u8,
-        amount: u64,
-        cliff_amount: u64,
-        is_cliff_percent: bool,
-        start: u64,
-        interval: u64,
-        rate: u64,
-        duration: u64,
-        is_infinite: bool,
-        cancel_by: u8,
-        pause_by: u8,
-        resume_by: u8,
-        withdraw_by: u8,
-        edit_by: u8,
-        start_now: bool,
-    ) -> Result<()> {
-        // Get Account
-        let stream_account = &mut ctx.accounts.stream;
+// Basis points in 100%, used for percentage cliffs and fees
+const MAX_BPS: u64 = 10_000;
 
-        let clock: Clock = Clock::get().unwrap();
-        let timestamp = clock.unix_timestamp as u64;
-        let mut start_time = start;
-        let mut amount = amount;
+// Seed of the ProgramConfig PDA
+pub const CONFIG_SEED: &[u8] = b"config";
 
-        if start_now == true {
-            start_time = timestamp;
-        }
+// Seed of the SplitAccount PDA, together with the stream
+pub const SPLIT_SEED: &[u8] = b"split";
 
-        // Stream Title shouldn't be longer than 50 characters
-        require!(stream_title.len() <= 50, MyError::TitleTooLong);
-
-        // Recipient shouldn't be same as Sender
-        require!(
-            ctx.accounts.recipient.key() != ctx.accounts.sender.key(),
-            MyError::SenderIsRecipient
-        );
-
-        // Amount to Stream should be greater than 0
-        require!(amount > 0, MyError::DepositIsZero);
-
-        // Start time of Stream should be in future
-        require!(start_time >= timestamp, MyError::PastStartTime);
-
-        // Interval of Stream should be greater than 0
-        require!(interval > 0, MyError::IntervalIsZero);
-
-        // Adjust for cliff amount
-        amount -= cliff_amount;
-
-        // Amount to Stream should be greater than the Rate of Stream
-        require!(amount >= rate, MyError::DepositSmallerThanTime);
-
-        require!(cancel_by <= 3, MyError::InvalidCancelBy);
-        require!(withdraw_by <= 2, MyError::InvalidWithdrawBy);
-        require!(edit_by <= 2, MyError::InvalidEditBy);
-        require!(pause_by <= 3, MyError::InvalidPauseBy);
-        require!(resume_by <= 3, MyError::InvalidResumeBy);
-
-        require!(
-            rate == ((amount as f64 / duration as f64) * interval as f64).round() as u64,
-            MyError::IncorrectDuration
-        );
-        let rem = amount % rate;
-        let no_of_intervals = amount / rate;
-
-        let new_duration = match rem {
-            1.. => interval * (no_of_intervals + 1),
-            0 => duration,
-        };
-
-        let stop = start_time + new_duration;
-
-        stream_account.stream_id = stream_id.clone();
-        stream_account.stream_title = stream_title;
-        stream_account.recipient = ctx.accounts.recipient.key();
-        stream_account.sender = ctx.accounts.sender.key();
-        stream_account.token_address = Pubkey::new(BLANK.as_bytes());
-        stream_account.create_time = timestamp;
-        stream_account.start_time = start_time;
-        stream_account.stop_time = stop;
-        stream_account.remaining_balance = amount + cliff_amount;
-        stream_account.deposit = amount + cliff_amount;
-        stream_account.withdrawn = 0;
-        stream_account.cliff_amount = cliff_amount;
-        stream_account.is_cliff_percent = is_cliff_percent;
-        stream_account.paused_amount = 0;
-        stream_account.interval = interval;
-        stream_account.rate_of_stream = rate;
-        stream_account.bump = *ctx.bumps.get("stream").unwrap();
-        stream_account.is_paused = false;
-        stream_account.is_infinite = is_infinite;
-        stream_account.is_cancelled = false;
-        stream_account.cancel_by = match cancel_by {
-            0 => StateChangeAuth::OnlySender,
-            1 => StateChangeAuth::OnlyReceiver,
-            2 => StateChangeAuth::Both,
-            _ => StateChangeAuth::Neither,
-        };
-        stream_account.pause_by = match pause_by {
-            0 => StateChangeAuth::OnlySender,
-            1 => StateChangeAuth::OnlyReceiver,
-            2 => StateChangeAuth::Both,
-            _ => StateChangeAuth::Neither,
-        };
-        stream_account.withdraw_by = match withdraw_by {
-            0 => StateChangeAuth::OnlySender,
-            1 => StateChangeAuth::OnlyReceiver,
-            _ => StateChangeAuth::Both,
-        };
-        stream_account.resume_by = match resume_by {
-            0 => StateChangeAuth::OnlySender,
-            1 => StateChangeAuth::OnlyReceiver,
-            2 => StateChangeAuth::Both,
-            _ => StateChangeAuth::Neither,
-        };
-        stream_account.edit_by = match edit_by {
-            0 => StateChangeAuth::OnlySender,
-            1 => StateChangeAuth::OnlyReceiver,
-            _ => StateChangeAuth::Both,
-        };
+declare_id!("F6ZLaARn1TvVHh15hSeymSh6r9XhbiFa5bLiceHWb87d");
 
-        let sender = ctx.accounts.sender.key();
+#[program]
+pub mod stream_contract {
 
-        let seeds = &[stream_id.as_bytes(), sender.as_ref(), &[bump]];
+    use super::*;
 
-        let ix = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.sender.key(),
-            &stream_account.key(),
-            amount + cliff_amount,
-        );
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        // The upgrade authority becomes the admin and can hand the role over afterwards
+        let config = &mut ctx.accounts.config;
 
-        anchor_lang::solana_program::program::invoke_signed(
-            &ix,
-            &[
-                ctx.accounts.sender.to_account_info(),
-                stream_account.to_account_info(),
-            ],
-            &[&seeds[..]],
-        )?;
+        config.admin = ctx.accounts.admin.key();
+        config.pending_admin = Pubkey::default();
+        config.is_halted = false;
+        config.allowed_mints = Vec::new();
+        config.fee_bps = 0;
+        config.treasury = ctx.accounts.admin.key();
+        config.bump = ctx.bumps.config;
 
         Ok(())
     }
 
-    pub fn create_stream_token(
-        ctx: Context<CreateStreamToken>,
-        stream_id: String,
-        stream_title: String,
-        values: Vec<u64>,
-        is_cliff_percent: bool,
-        is_infinite: bool,
-        cancel_by: u8,
-        pause_by: u8,
-        resume_by: u8,
-        withdraw_by: u8,
-        edit_by: u8,
-        start_now: bool,
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        is_halted: bool,
+        allowed_mints: Vec<Pubkey>,
+        fee_bps: u64,
+        treasury: Pubkey,
     ) -> Result<()> {
-        let stream_account = &mut ctx.accounts.stream;
-
-        let mut amount = values[0];
-        let cliff_amount = values[1];
-        let mut start = values[2];
-        let interval = values[3];
-        let rate = values[4];
-        let duration = values[5];
-        let clock: Clock = Clock::get().unwrap();
-        let timestamp = clock.unix_timestamp as u64;
-
-        if start_now == true {
-            start = timestamp;
-        }
+        let config = &mut ctx.accounts.config;
 
-        // Stream Title shouldn't be longer than 50 characters
-        require!(stream_title.len() <= 50, MyError::TitleTooLong);
-
-        // Recipient shouldn't be same as Sender
         require!(
-            ctx.accounts.recipient.key() != ctx.accounts.sender.key(),
-            MyError::SenderIsRecipient
+            config.admin == ctx.accounts.admin.key(),
+            MyError::NotAuthorized
         );
-
-        // Amount to Stream should be greater than 0
-        require!(amount > 0, MyError::DepositIsZero);
-
-        // Start time of Stream should be in future
-        require!(start >= timestamp, MyError::PastStartTime);
-
-        // Interval of Stream should be greater than 0
-        require!(interval > 0, MyError::IntervalIsZero);
-
-        // Adjust for cliff amount
-        amount -= cliff_amount;
-
-        // Amount to Stream should be greater than the Rate of Stream
-        require!(amount >= rate, MyError::DepositSmallerThanTime);
-
-        require!(cancel_by <= 3, MyError::InvalidCancelBy);
-        require!(pause_by <= 3, MyError::InvalidPauseBy);
-        require!(withdraw_by <= 2, MyError::InvalidWithdrawBy);
-        require!(resume_by <= 3, MyError::InvalidResumeBy);
-        require!(edit_by <= 2, MyError::InvalidEditBy);
-
         require!(
-            rate == ((amount as f64 / duration as f64) * interval as f64).round() as u64,
-            MyError::IncorrectDuration
+            allowed_mints.len() <= ProgramConfig::MAX_MINTS,
+            MyError::TooManyMints
         );
-        let rem = amount % rate;
-        let no_of_intervals = amount / rate;
+        require!(fee_bps <= ProgramConfig::MAX_FEE_BPS, MyError::FeeTooHigh);
+        require!(treasury != Pubkey::default(), MyError::InvalidTreasury);
 
-        let new_duration = match rem {
-            1.. => interval * (no_of_intervals + 1),
-            0 => duration,
-        };
-
-        let stop = start + new_duration;
-
-        stream_account.stream_id = stream_id;
-        stream_account.stream_title = stream_title;
-        stream_account.recipient = ctx.accounts.recipient.key();
-        stream_account.sender = ctx.accounts.sender.key();
-        stream_account.token_address = ctx.accounts.token_address.key();
-        stream_account.create_time = timestamp;
-        stream_account.start_time = start;
-        stream_account.stop_time = stop;
-        stream_account.remaining_balance = amount + cliff_amount;
-        stream_account.deposit = amount + cliff_amount;
-        stream_account.withdrawn = 0;
-        stream_account.cliff_amount = cliff_amount;
-        stream_account.is_cliff_percent = is_cliff_percent;
-        stream_account.paused_amount = 0;
-        stream_account.interval = interval;
-        stream_account.rate_of_stream = rate;
-        stream_account.bump = *ctx.bumps.get("stream").unwrap();
-        stream_account.is_paused = false;
-        stream_account.is_infinite = is_infinite;
-        stream_account.is_cancelled = false;
-        stream_account.cancel_by = match cancel_by {
-            0 => StateChangeAuth::OnlySender,
-            1 => StateChangeAuth::OnlyReceiver,
-            2 => StateChangeAuth::Both,
-            _ => StateChangeAuth::Neither,
-        };
-        stream_account.pause_by = match pause_by {
-            0 => StateChangeAuth::OnlySender,
-            1 => StateChangeAuth::OnlyReceiver,
-            2 => StateChangeAuth::Both,
-            _ => StateChangeAuth::Neither,
-        };
-        stream_account.withdraw_by = match withdraw_by {
-            0 => StateChangeAuth::OnlySender,
-            1 => StateChangeAuth::OnlyReceiver,
-            _ => StateChangeAuth::Both,
-        };
-        stream_account.resume_by = match resume_by {
-            0 => StateChangeAuth::OnlySender,
-            1 => StateChangeAuth::OnlyReceiver,
-            2 => StateChangeAuth::Both,
-            _ => StateChangeAuth::Neither,
-        };
-        stream_account.edit_by = match edit_by {
-            0 => StateChangeAuth::OnlySender,
-            1 => StateChangeAuth::OnlyReceiver,
-            _ => StateChangeAuth::Both,
-        };
-
-        transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.sender_tokens.clone().to_account_info(),
-                    to: ctx.accounts.stream_tokens.to_account_info(),
-                    authority: ctx.accounts.sender.clone().to_account_info(),
-                },
-            ),
-            amount + cliff_amount,
-        )?;
+        config.is_halted = is_halted;
+        config.allowed_mints = allowed_mints;
+        config.fee_bps = fee_bps;
+        config.treasury = treasury;
 
         Ok(())
     }
 
-    pub fn withdraw_from_stream(ctx: Context<WithdrawFromStream>, stream_id: String) -> Result<()> {
-        let stream_account = &mut ctx.accounts.stream;
+    pub fn transfer_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
 
         require!(
-            (stream_account.sender == ctx.accounts.authority.key()
-                && stream_account.withdraw_by != StateChangeAuth::OnlyReceiver)
-                || (stream_account.recipient == ctx.accounts.authority.key()
-                    && stream_account.withdraw_by != StateChangeAuth::OnlySender),
+            config.admin == ctx.accounts.admin.key(),
             MyError::NotAuthorized
         );
-        require!(
-            stream_account.stream_id == stream_id,
-            MyError::IncorrectStreamId
-        );
-        require!(stream_account.is_paused == false, MyError::StreamIsPaused);
-        require!(
-            ctx.accounts.recipient.key() == stream_account.recipient,
-            MyError::IncorrectRecipient
-        );
-        let clock: Clock = Clock::get().unwrap();
-        let timestamp = clock.unix_timestamp as u64;
-        let start = stream_account.start_time;
-        let stop = stream_account.stop_time;
-        let interval = stream_account.interval;
-
-        require!(timestamp >= start, MyError::StreamNotStarted);
-
-        let mut ready_for_withdrawal: u64;
-        let amt_withdrawn = stream_account.withdrawn;
 
-        if timestamp >= stop {
-            ready_for_withdrawal = stream_account.remaining_balance;
-        } else {
-            let cliff = stream_account.cliff_amount;
-            let delta = timestamp - start;
-
-            if cliff == 0 {
-                require!(delta >= interval, MyError::NothingToWithdraw);
-            }
-
-            let no_of_intervals = delta / interval;
-
-            ready_for_withdrawal = no_of_intervals * stream_account.rate_of_stream;
-
-            if stream_account.paused_amount > 0 {
-                ready_for_withdrawal += stream_account.paused_amount;
-            } else {
-                ready_for_withdrawal += cliff;
-            }
-
-            if amt_withdrawn > 0 {
-                ready_for_withdrawal -= amt_withdrawn;
-            }
-        }
-
-        require!(ready_for_withdrawal > 0, MyError::NothingToWithdraw);
-
-        let amt = ready_for_withdrawal;
-
-        **stream_account.to_account_info().try_borrow_mut_lamports()? -= amt;
-        **ctx.accounts.recipient.try_borrow_mut_lamports()? += amt;
-
-        stream_account.remaining_balance -= amt;
-        stream_account.withdrawn += amt;
+        // Takes effect only once the new admin accepts
+        config.pending_admin = new_admin;
 
         Ok(())
     }
 
-    pub fn withdraw_from_stream_token(
-        ctx: Context<WithdrawFromStreamToken>,
-        stream_id: String,
-    ) -> Result<()> {
-        let stream_account = &mut ctx.accounts.stream;
+    pub fn accept_admin(ctx: Context<UpdateConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
 
         require!(
-            (stream_account.sender == ctx.accounts.authority.key()
-                && stream_account.withdraw_by != StateChangeAuth::OnlyReceiver)
-                || (stream_account.recipient == ctx.accounts.authority.key()
-                    && stream_account.withdraw_by != StateChangeAuth::OnlySender),
+            config.pending_admin != Pubkey::default()
+                && config.pending_admin == ctx.accounts.admin.key(),
             MyError::NotAuthorized
         );
-        require!(
-            stream_account.stream_id == stream_id,
-            MyError::IncorrectStreamId
-        );
-        require!(stream_account.is_paused == false, MyError::StreamIsPaused);
-        require!(
-            ctx.accounts.recipient.key() == stream_account.recipient,
-            MyError::IncorrectRecipient
-        );
-        require!(
-            ctx.accounts.token_address.key() == stream_account.token_address,
-            MyError::IncorrectTokenAddress
-        );
-        require!(
-            ctx.accounts.sender.key() == stream_account.sender,
-            MyError::IncorrectSender
-        );
-        let recipient_tokens = get_associated_token_address(
-            &ctx.accounts.recipient.key(),
-            &stream_account.token_address,
-        );
-        require!(
-            ctx.accounts.recipient_tokens.key() == recipient_tokens,
-            MyError::AssociatedTokenAccountIncorrect
-        );
-
-        let clock: Clock = Clock::get().unwrap();
-        let timestamp = clock.unix_timestamp as u64;
-        let start = stream_account.start_time;
-        let stop = stream_account.stop_time;
-        let interval = stream_account.interval;
-
-        require!(timestamp >= start, MyError::StreamNotStarted);
-
-        let mut ready_for_withdrawal: u64;
-        let amt_withdrawn = stream_account.withdrawn;
-
-        if timestamp >= stop {
-            ready_for_withdrawal = stream_account.remaining_balance;
-        } else {
-            let cliff = stream_account.cliff_amount;
-            let delta = timestamp - start;
 
-            if cliff == 0 {
-                require!(delta >= interval, MyError::NothingToWithdraw);
-            }
+        config.admin = config.pending_admin;
+        config.pending_admin = Pubkey::default();
 
-            let no_of_intervals = delta / interval;
-
-            ready_for_withdrawal = no_of_intervals * stream_account.rate_of_stream;
+        Ok(())
+    }
 
-            if stream_account.paused_amount > 0 {
-                ready_for_withdrawal += stream_account.paused_amount;
-            } else {
-                ready_for_withdrawal += cliff;
-            }
+    pub fn create_stream(ctx: Context<CreateStream>, params: CreateStreamParams) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+        stream.set_inner(build_stream_account(
+            &params,
+            ctx.accounts.sender.key(),
+            ctx.accounts.recipient.key(),
+            Pubkey::default(),
+            ctx.bumps.stream,
+            engine::now()?,
+        )?);
+
+        let sender = ctx.accounts.sender.to_account_info();
+        let vault = AssetVault::native(stream, &ctx.accounts.system_program.to_account_info())?;
+        engine::open(stream, &ctx.accounts.config, &vault, &sender, &sender)
+    }
 
-            if amt_withdrawn > 0 {
-                ready_for_withdrawal -= amt_withdrawn;
-            }
-        }
+    pub fn create_stream_token(
+        ctx: Context<CreateStreamToken>,
+        params: CreateStreamParams,
+    ) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+        stream.set_inner(build_stream_account(
+            &params,
+            ctx.accounts.sender.key(),
+            ctx.accounts.recipient.key(),
+            ctx.accounts.token_address.key(),
+            ctx.bumps.stream,
+            engine::now()?,
+        )?);
+
+        let vault = AssetVault::token(
+            stream,
+            &ctx.accounts.stream_tokens.to_account_info(),
+            &ctx.accounts.token_address.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+        engine::open(
+            stream,
+            &ctx.accounts.config,
+            &vault,
+            &ctx.accounts.sender_tokens.to_account_info(),
+            &ctx.accounts.sender.to_account_info(),
+        )
+    }
 
-        require!(ready_for_withdrawal > 0, MyError::NothingToWithdraw);
+    pub fn create_step_stream(
+        ctx: Context<CreateStepStream>,
+        params: CreateStreamParams,
+        checkpoints: Vec<Checkpoint>,
+    ) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+        stream.set_inner(build_step_stream_account(
+            &params,
+            checkpoints,
+            ctx.accounts.sender.key(),
+            ctx.accounts.recipient.key(),
+            Pubkey::default(),
+            ctx.bumps.stream,
+            engine::now()?,
+        )?);
+
+        let sender = ctx.accounts.sender.to_account_info();
+        let vault = AssetVault::native(stream, &ctx.accounts.system_program.to_account_info())?;
+        engine::open(stream, &ctx.accounts.config, &vault, &sender, &sender)
+    }
 
-        let amt = ready_for_withdrawal;
+    pub fn create_step_stream_token(
+        ctx: Context<CreateStepStreamToken>,
+        params: CreateStreamParams,
+        checkpoints: Vec<Checkpoint>,
+    ) -> Result<()> {
+        let stream = &mut ctx.accounts.stream;
+        stream.set_inner(build_step_stream_account(
+            &params,
+            checkpoints,
+            ctx.accounts.sender.key(),
+            ctx.accounts.recipient.key(),
+            ctx.accounts.token_address.key(),
+            ctx.bumps.stream,
+            engine::now()?,
+        )?);
+
+        let vault = AssetVault::token(
+            stream,
+            &ctx.accounts.stream_tokens.to_account_info(),
+            &ctx.accounts.token_address.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+        engine::open(
+            stream,
+            &ctx.accounts.config,
+            &vault,
+            &ctx.accounts.sender_tokens.to_account_info(),
+            &ctx.accounts.sender.to_account_info(),
+        )
+    }
 
+    pub fn create_split_stream(
+        ctx: Context<CreateSplitStream>,
+        params: CreateStreamParams,
+        recipients: Vec<SplitRecipient>,
+    ) -> Result<()> {
         let sender = ctx.accounts.sender.key();
-        let bump = stream_account.bump;
-
-        let seeds = &[stream_id.as_bytes(), sender.as_ref(), &[bump]];
+        let split = &mut ctx.accounts.split;
+        split.set_inner(build_split_account(
+            ctx.accounts.stream.key(),
+            sender,
+            &recipients,
+            ctx.bumps.split,
+        )?);
+
+        let stream = &mut ctx.accounts.stream;
+        stream.set_inner(build_stream_account(
+            &params,
+            sender,
+            split.key(),
+            Pubkey::default(),
+            ctx.bumps.stream,
+            engine::now()?,
+        )?);
+        stream.is_split = true;
+
+        let sender = ctx.accounts.sender.to_account_info();
+        let vault = AssetVault::native(stream, &ctx.accounts.system_program.to_account_info())?;
+        engine::open(stream, &ctx.accounts.config, &vault, &sender, &sender)
+    }
 
-        transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.stream_tokens.clone().to_account_info(),
-                    to: ctx.accounts.recipient_tokens.to_account_info(),
-                    authority: stream_account.to_account_info(),
-                },
-                &[&seeds[..]],
-            ),
-            amt,
+    pub fn create_split_stream_token(
+        ctx: Context<CreateSplitStreamToken>,
+        params: CreateStreamParams,
+        recipients: Vec<SplitRecipient>,
+    ) -> Result<()> {
+        let sender = ctx.accounts.sender.key();
+        let split = &mut ctx.accounts.split;
+        split.set_inner(build_split_account(
+            ctx.accounts.stream.key(),
+            sender,
+            &recipients,
+            ctx.bumps.split,
+        )?);
+
+        let stream = &mut ctx.accounts.stream;
+        stream.set_inner(build_stream_account(
+            &params,
+            sender,
+            split.key(),
+            ctx.accounts.token_address.key(),
+            ctx.bumps.stream,
+            engine::now()?,
+        )?);
+        stream.is_split = true;
+
+        let vault = AssetVault::token(
+            stream,
+            &ctx.accounts.stream_tokens.to_account_info(),
+            &ctx.accounts.token_address.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
         )?;
-
-        stream_account.remaining_balance -= amt;
-        stream_account.withdrawn += amt;
-
-        Ok(())
+        engine::open(
+            stream,
+            &ctx.accounts.config,
+            &vault,
+            &ctx.accounts.sender_tokens.to_account_info(),
+            &ctx.accounts.sender.to_account_info(),
+        )
     }
 
-    pub fn cancel_stream(ctx: Context<CancelStream>, stream_id: String) -> Result<()> {
-        let stream_account = &mut ctx.accounts.stream;
-
-        require!(
-            stream_account.cancel_by != StateChangeAuth::Neither,
-            MyError::NotAuthorized
-        );
-        require!(
-            (stream_account.sender == ctx.accounts.authority.key()
-                && stream_account.cancel_by != StateChangeAuth::OnlyReceiver)
-                || (stream_account.recipient == ctx.accounts.authority.key()
-                    && stream_account.cancel_by != StateChangeAuth::OnlySender),
-            MyError::NotAuthorized
-        );
-        require!(
-            stream_account.stream_id == stream_id,
-            MyError::IncorrectStreamId
-        );
-        require!(
-            ctx.accounts.recipient.key() == stream_account.recipient,
-            MyError::IncorrectRecipient
-        );
-        require!(
-            ctx.accounts.sender.key() == stream_account.sender,
-            MyError::IncorrectSender
-        );
+    // Every vault is a separate account funded by its own transfer out of sender_tokens,
+    // all within this instruction, so the batch succeeds or fails as a whole. Debiting the
+    // sum once into a staging account would not save any Token-2022 transfer fee, as the
+    // fee is charged on that debit and then again on every transfer out to a vault.
+    pub fn create_streams_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateStreamsBatch<'info>>,
+        params: Vec<CreateStreamParams>,
+    ) -> Result<()> {
+        // Every stream needs its PDA, its recipient and its vault, in that order
         require!(
-            stream_account.is_cancelled == false,
-            MyError::StreamAlreadyCancelled
+            !params.is_empty() && ctx.remaining_accounts.len() == params.len() * 3,
+            MyError::InvalidBatch
         );
 
-        let clock: Clock = Clock::get().unwrap();
-        let timestamp = clock.unix_timestamp as u64;
-        let start = stream_account.start_time;
-        let stop = stream_account.stop_time;
-        let interval = stream_account.interval;
-
-        if timestamp < start || stream_account.is_paused == true {
-            let total_balance = stream_account.remaining_balance;
-
-            **stream_account.to_account_info().try_borrow_mut_lamports()? -= total_balance;
-            **ctx.accounts.sender.try_borrow_mut_lamports()? += total_balance;
-            stream_account.remaining_balance = 0;
-            stream_account.is_paused = false;
-        } else {
-            let mut ready_for_withdrawal: u64;
-            let amt_withdrawn = stream_account.withdrawn;
-
-            if timestamp >= stop {
-                ready_for_withdrawal = stream_account.remaining_balance;
-            } else {
-                let cliff = stream_account.cliff_amount;
-                let delta = timestamp - start;
-
-                let no_of_intervals = delta / interval;
-
-                ready_for_withdrawal = no_of_intervals * stream_account.rate_of_stream;
-
-                if stream_account.paused_amount > 0 {
-                    ready_for_withdrawal += stream_account.paused_amount;
-                } else {
-                    ready_for_withdrawal += cliff;
-                }
-
-                if amt_withdrawn > 0 {
-                    ready_for_withdrawal -= amt_withdrawn;
-                }
-            }
-
-            let total_balance = stream_account.remaining_balance;
-            let recipient_balance = ready_for_withdrawal;
-            let sender_balance = total_balance - recipient_balance;
-
-            **stream_account.to_account_info().try_borrow_mut_lamports()? -= total_balance;
-            **ctx.accounts.recipient.try_borrow_mut_lamports()? += recipient_balance;
-            **ctx.accounts.sender.try_borrow_mut_lamports()? += sender_balance;
-
-            stream_account.withdrawn += recipient_balance;
-            stream_account.remaining_balance = 0;
-        }
-        stream_account.is_cancelled = true;
-        Ok(())
-    }
-
-    pub fn cancel_stream_token(ctx: Context<CancelStreamToken>, stream_id: String) -> Result<()> {
-        let stream_account = &mut ctx.accounts.stream;
+        let timestamp = engine::now()?;
+        let sender = ctx.accounts.sender.key();
+        let token_address = ctx.accounts.token_address.key();
+        let mint = ctx.accounts.token_address.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let space = 16 + StreamAccount::MAX_SIZE;
+
+        for (stream_params, accounts) in params.iter().zip(ctx.remaining_accounts.chunks_exact(3)) {
+            let stream_info = &accounts[0];
+            let recipient = &accounts[1];
+            let stream_tokens = &accounts[2];
+
+            let (stream_key, bump) = Pubkey::find_program_address(
+                &[stream_params.stream_id.as_bytes(), sender.as_ref()],
+                ctx.program_id,
+            );
+            require!(stream_info.key() == stream_key, MyError::IncorrectStreamId);
+
+            let stream_account = build_stream_account(
+                stream_params,
+                sender,
+                recipient.key(),
+                token_address,
+                bump,
+                timestamp,
+            )?;
 
-        require!(
-            stream_account.cancel_by != StateChangeAuth::Neither,
-            MyError::NotAuthorized
-        );
-        require!(
-            (stream_account.sender == ctx.accounts.authority.key()
-                && stream_account.cancel_by != StateChangeAuth::OnlyReceiver)
-                || (stream_account.recipient == ctx.accounts.authority.key()
-                    && stream_account.cancel_by != StateChangeAuth::OnlySender),
-            MyError::NotAuthorized
-        );
-        require!(
-            stream_account.stream_id == stream_id,
-            MyError::IncorrectStreamId
-        );
-        require!(
-            ctx.accounts.token_address.key() == stream_account.token_address,
-            MyError::IncorrectTokenAddress
-        );
-        require!(
-            stream_account.is_cancelled == false,
-            MyError::StreamAlreadyCancelled
-        );
-        let recipient_tokens = get_associated_token_address(
-            &ctx.accounts.recipient.key(),
-            &ctx.accounts.token_address.key(),
-        );
-        require!(
-            ctx.accounts.recipient_tokens.key() == recipient_tokens,
-            MyError::AssociatedTokenAccountIncorrect
-        );
+            let seeds = &[stream_params.stream_id.as_bytes(), sender.as_ref(), &[bump]];
 
-        let clock: Clock = Clock::get().unwrap();
-        let timestamp = clock.unix_timestamp as u64;
-        let start = stream_account.start_time;
-        let stop = stream_account.stop_time;
-        let interval = stream_account.interval;
-
-        let sender = stream_account.sender;
-
-        let seeds = &[
-            stream_id.as_bytes(),
-            sender.as_ref(),
-            &[stream_account.bump],
-        ];
-
-        if timestamp < start || stream_account.is_paused == true {
-            let total_balance = stream_account.remaining_balance;
-
-            transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.stream_tokens.clone().to_account_info(),
-                        to: ctx.accounts.sender_tokens.to_account_info(),
-                        authority: stream_account.to_account_info(),
-                    },
-                    &[&seeds[..]],
-                ),
-                total_balance,
+            // Both addresses are predictable, whatever was sent to them beforehand is kept
+            engine::create_pda_account(
+                &ctx.accounts.sender.to_account_info(),
+                stream_info,
+                &ctx.accounts.system_program.to_account_info(),
+                seeds,
+                space,
+                ctx.program_id,
             )?;
-            stream_account.remaining_balance = 0;
-            stream_account.is_paused = false;
-        } else {
-            let mut ready_for_withdrawal: u64;
-            let amt_withdrawn = stream_account.withdrawn;
-
-            if timestamp >= stop {
-                ready_for_withdrawal = stream_account.remaining_balance;
-            } else {
-                let cliff = stream_account.cliff_amount;
-                let delta = timestamp - start;
-
-                let no_of_intervals = delta / interval;
-
-                ready_for_withdrawal = no_of_intervals * stream_account.rate_of_stream;
-
-                if stream_account.paused_amount > 0 {
-                    ready_for_withdrawal += stream_account.paused_amount;
-                } else {
-                    ready_for_withdrawal += cliff;
-                }
-
-                if amt_withdrawn > 0 {
-                    ready_for_withdrawal -= amt_withdrawn;
-                }
-            }
-
-            let total_balance = stream_account.remaining_balance;
-            let recipient_balance = ready_for_withdrawal;
-            let sender_balance = total_balance - recipient_balance;
-
-            transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.stream_tokens.clone().to_account_info(),
-                        to: ctx.accounts.recipient_tokens.to_account_info(),
-                        authority: stream_account.to_account_info(),
-                    },
-                    &[&seeds[..]],
-                ),
-                recipient_balance,
-            )?;
-
-            transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.stream_tokens.clone().to_account_info(),
-                        to: ctx.accounts.sender_tokens.to_account_info(),
-                        authority: stream_account.to_account_info(),
-                    },
-                    &[&seeds[..]],
-                ),
-                sender_balance,
+            stream_account.try_serialize(&mut &mut stream_info.try_borrow_mut_data()?[..])?;
+
+            create_idempotent(CpiContext::new(
+                ctx.accounts.associated_token_program.to_account_info(),
+                Create {
+                    payer: ctx.accounts.sender.to_account_info(),
+                    associated_token: stream_tokens.clone(),
+                    authority: stream_info.clone(),
+                    mint: mint.clone(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    token_program: token_program.clone(),
+                },
+            ))?;
+
+            let mut stream: Account<StreamAccount> = Account::try_from(stream_info)?;
+            let vault = AssetVault::token(&stream, stream_tokens, &mint, &token_program)?;
+            engine::open(
+                &mut stream,
+                &ctx.accounts.config,
+                &vault,
+                &ctx.accounts.sender_tokens.to_account_info(),
+                &ctx.accounts.sender.to_account_info(),
             )?;
-            stream_account.withdrawn += recipient_balance;
-            stream_account.remaining_balance = 0;
+            stream.exit(ctx.program_id)?;
         }
-        stream_account.is_cancelled = true;
+
         Ok(())
     }
 
-    pub fn pause_stream(ctx: Context<PauseStream>, stream_id: String) -> Result<()> {
-        let stream_account = &mut ctx.accounts.stream;
-
-        require!(
-            stream_account.pause_by != StateChangeAuth::Neither,
-            MyError::NotAuthorized
-        );
-        require!(
-            (stream_account.sender == ctx.accounts.authority.key()
-                && stream_account.pause_by != StateChangeAuth::OnlyReceiver)
-                || (stream_account.recipient == ctx.accounts.authority.key()
-                    && stream_account.pause_by != StateChangeAuth::OnlySender),
-            MyError::NotAuthorized
-        );
-        require!(
-            stream_account.stream_id == stream_id,
-            MyError::IncorrectStreamId
-        );
-        require!(
-            stream_account.is_paused == false,
-            MyError::StreamAlreadyPaused
-        );
-        require!(
-            stream_account.is_cancelled == false,
-            MyError::StreamAlreadyCancelled
-        );
-
-        let clock: Clock = Clock::get().unwrap();
-        let timestamp = clock.unix_timestamp as u64;
-        let start = stream_account.start_time;
-        let stop = stream_account.stop_time;
-        let interval = stream_account.interval;
-
-        require!(timestamp < stop, MyError::StreamAlreadyEnded);
-        require!(timestamp >= start, MyError::StreamNotStarted);
-
-        let cliff = stream_account.cliff_amount;
-        let delta = timestamp - start;
-        let time_left = stop - timestamp;
+    pub fn withdraw_from_stream(
+        ctx: Context<WithdrawFromStream>,
+        _stream_id: String,
+        amount: Option<u64>,
+    ) -> Result<()> {
+        let vault = AssetVault::native(
+            &ctx.accounts.stream,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.recipient.clone(),
+            treasury: ctx.accounts.treasury.clone(),
+            partner: ctx.accounts.partner.clone(),
+        };
 
-        let no_of_intervals = delta / interval;
+        engine::withdraw(
+            &mut ctx.accounts.stream,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            ctx.accounts.authority.key(),
+            amount,
+        )?;
 
-        let mut ready_for_withdrawal = no_of_intervals * stream_account.rate_of_stream;
-        let amt_withdrawn = stream_account.withdrawn;
+        Ok(())
+    }
 
-        if stream_account.paused_amount > 0 {
-            ready_for_withdrawal += stream_account.paused_amount;
-        } else {
-            ready_for_withdrawal += cliff;
-        }
+    pub fn withdraw_from_stream_token(
+        ctx: Context<WithdrawFromStreamToken>,
+        _stream_id: String,
+        amount: Option<u64>,
+    ) -> Result<()> {
+        let vault = AssetVault::token(
+            &ctx.accounts.stream,
+            &ctx.accounts.stream_tokens.to_account_info(),
+            &ctx.accounts.token_address.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.recipient_tokens.to_account_info(),
+            treasury: ctx
+                .accounts
+                .treasury_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+            partner: ctx
+                .accounts
+                .partner_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+        };
 
-        if amt_withdrawn > 0 {
-            ready_for_withdrawal -= amt_withdrawn;
-        }
+        engine::withdraw(
+            &mut ctx.accounts.stream,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            ctx.accounts.authority.key(),
+            amount,
+        )?;
 
-        let recipient_balance = ready_for_withdrawal;
+        Ok(())
+    }
 
-        if recipient_balance > 0 {
-            **stream_account.to_account_info().try_borrow_mut_lamports()? -= recipient_balance;
-            **ctx.accounts.recipient.try_borrow_mut_lamports()? += recipient_balance;
-        }
+    pub fn withdraw_from_stream_to(
+        ctx: Context<WithdrawFromStreamTo>,
+        _stream_id: String,
+        amount: Option<u64>,
+    ) -> Result<()> {
+        let vault = AssetVault::native(
+            &ctx.accounts.stream,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.destination.clone(),
+            treasury: ctx.accounts.treasury.clone(),
+            partner: ctx.accounts.partner.clone(),
+        };
 
-        stream_account.is_paused = true;
-        stream_account.time_left = time_left;
-        stream_account.remaining_balance -= recipient_balance;
-        stream_account.withdrawn += recipient_balance;
-        stream_account.paused_amount = stream_account.withdrawn;
+        engine::withdraw(
+            &mut ctx.accounts.stream,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            ctx.accounts.recipient.key(),
+            amount,
+        )?;
 
         Ok(())
     }
 
-    pub fn pause_stream_token(ctx: Context<PauseStreamToken>, stream_id: String) -> Result<()> {
-        let stream_account = &mut ctx.accounts.stream;
-
-        require!(
-            stream_account.pause_by != StateChangeAuth::Neither,
-            MyError::NotAuthorized
-        );
-        require!(
-            (stream_account.sender == ctx.accounts.authority.key()
-                && stream_account.pause_by != StateChangeAuth::OnlyReceiver)
-                || (stream_account.recipient == ctx.accounts.authority.key()
-                    && stream_account.pause_by != StateChangeAuth::OnlySender),
-            MyError::NotAuthorized
-        );
-        require!(
-            stream_account.stream_id == stream_id,
-            MyError::IncorrectStreamId
-        );
-        require!(
-            ctx.accounts.recipient.key() == stream_account.recipient,
-            MyError::IncorrectRecipient
-        );
-        require!(
-            ctx.accounts.token_address.key() == stream_account.token_address,
-            MyError::IncorrectTokenAddress
-        );
-        require!(
-            ctx.accounts.sender.key() == stream_account.sender,
-            MyError::IncorrectSender
-        );
-        require!(
-            stream_account.is_paused == false,
-            MyError::StreamAlreadyPaused
-        );
-        require!(
-            stream_account.is_cancelled == false,
-            MyError::StreamAlreadyEnded
-        );
-        let recipient_tokens = get_associated_token_address(
-            &ctx.accounts.recipient.key(),
-            &ctx.accounts.token_address.key(),
-        );
-        require!(
-            ctx.accounts.recipient_tokens.key() == recipient_tokens,
-            MyError::AssociatedTokenAccountIncorrect
-        );
+    pub fn withdraw_from_stream_token_to(
+        ctx: Context<WithdrawFromStreamTokenTo>,
+        _stream_id: String,
+        amount: Option<u64>,
+    ) -> Result<()> {
+        let vault = AssetVault::token(
+            &ctx.accounts.stream,
+            &ctx.accounts.stream_tokens.to_account_info(),
+            &ctx.accounts.token_address.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.destination_tokens.to_account_info(),
+            treasury: ctx
+                .accounts
+                .treasury_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+            partner: ctx
+                .accounts
+                .partner_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+        };
 
-        let clock: Clock = Clock::get().unwrap();
-        let timestamp = clock.unix_timestamp as u64;
-        let start = stream_account.start_time;
-        let stop = stream_account.stop_time;
-        let interval = stream_account.interval;
+        engine::withdraw(
+            &mut ctx.accounts.stream,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            ctx.accounts.recipient.key(),
+            amount,
+        )?;
 
-        require!(timestamp < stop, MyError::StreamAlreadyEnded);
-        require!(timestamp >= start, MyError::StreamNotStarted);
+        Ok(())
+    }
 
-        let cliff = stream_account.cliff_amount;
-        let delta = timestamp - start;
-        let time_left = stop - timestamp;
+    pub fn withdraw_from_split(ctx: Context<WithdrawFromSplit>, _stream_id: String) -> Result<()> {
+        let vault = AssetVault::native(
+            &ctx.accounts.stream,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.recipient.to_account_info(),
+            treasury: ctx.accounts.treasury.clone(),
+            partner: ctx.accounts.partner.clone(),
+        };
 
-        let no_of_intervals = delta / interval;
+        engine::withdraw_share(
+            &mut ctx.accounts.stream,
+            &mut ctx.accounts.split,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            ctx.accounts.recipient.key(),
+        )?;
 
-        let mut ready_for_withdrawal = no_of_intervals * stream_account.rate_of_stream;
-        let amt_withdrawn = stream_account.withdrawn;
+        Ok(())
+    }
 
-        if stream_account.paused_amount > 0 {
-            ready_for_withdrawal += stream_account.paused_amount;
-        } else {
-            ready_for_withdrawal += cliff;
-        }
+    pub fn withdraw_from_split_token(
+        ctx: Context<WithdrawFromSplitToken>,
+        _stream_id: String,
+    ) -> Result<()> {
+        let vault = AssetVault::token(
+            &ctx.accounts.stream,
+            &ctx.accounts.stream_tokens.to_account_info(),
+            &ctx.accounts.token_address.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.recipient_tokens.to_account_info(),
+            treasury: ctx
+                .accounts
+                .treasury_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+            partner: ctx
+                .accounts
+                .partner_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+        };
 
-        if amt_withdrawn > 0 {
-            ready_for_withdrawal -= amt_withdrawn;
-        }
+        engine::withdraw_share(
+            &mut ctx.accounts.stream,
+            &mut ctx.accounts.split,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            ctx.accounts.recipient.key(),
+        )?;
 
-        let recipient_balance = ready_for_withdrawal;
+        Ok(())
+    }
 
-        let sender = ctx.accounts.sender.key();
-        let bump = stream_account.bump;
-
-        let seeds = &[stream_id.as_bytes(), sender.as_ref(), &[bump]];
-
-        if recipient_balance > 0 {
-            transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.stream_tokens.clone().to_account_info(),
-                        to: ctx.accounts.recipient_tokens.to_account_info(),
-                        authority: stream_account.to_account_info(),
-                    },
-                    &[&seeds[..]],
-                ),
-                recipient_balance,
+    pub fn withdraw_from_streams_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawFromStreamsBatch<'info>>,
+    ) -> Result<()> {
+        // Every stream needs its mint, its vault, a token account of the recipient and the
+        // associated token accounts of the treasury and of its partner, in that order. The
+        // program id stands in for the treasury when the protocol charges no fee, and for
+        // the partner when the stream pays no partner fee
+        let batch = ctx.remaining_accounts.chunks_exact(6);
+        require!(
+            !ctx.remaining_accounts.is_empty() && batch.remainder().is_empty(),
+            MyError::InvalidBatch
+        );
+
+        let recipient = ctx.accounts.recipient.key();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let mut summary: Vec<BatchWithdrawal> = Vec::new();
+
+        for accounts in batch {
+            let mut stream_account: Account<StreamAccount> = Account::try_from(&accounts[0])?;
+            let recipient_tokens: InterfaceAccount<TokenAccount> =
+                InterfaceAccount::try_from(&accounts[3])?;
+
+            require!(
+                stream_account.recipient == recipient,
+                MyError::NotAuthorized
+            );
+            require!(
+                recipient_tokens.mint == stream_account.token_address
+                    && recipient_tokens.owner == recipient,
+                MyError::InvalidDestination
+            );
+
+            let vault =
+                AssetVault::token(&stream_account, &accounts[2], &accounts[1], &token_program)?;
+            let payees = Payees {
+                recipient: accounts[3].clone(),
+                treasury: (accounts[4].key() != crate::ID).then(|| accounts[4].clone()),
+                partner: (accounts[5].key() != crate::ID).then(|| accounts[5].clone()),
+            };
+
+            // Streams with nothing to withdraw right now are skipped, not failed
+            let payout = engine::withdraw_if_accrued(
+                &mut stream_account,
+                &ctx.accounts.config,
+                &vault,
+                &payees,
+                recipient,
             )?;
+            stream_account.exit(ctx.program_id)?;
+
+            summary.push(BatchWithdrawal {
+                stream: stream_account.key(),
+                amount: payout.amount,
+                protocol_fee: payout.protocol_fee,
+                partner_fee: payout.partner_fee,
+                transfer_fee: payout.transfer_fee,
+            });
         }
 
-        stream_account.is_paused = true;
-        stream_account.time_left = time_left;
-        stream_account.remaining_balance -= recipient_balance;
-        stream_account.withdrawn += recipient_balance;
-        stream_account.paused_amount = stream_account.withdrawn;
+        set_return_data(&summary.try_to_vec()?);
 
         Ok(())
     }
 
-    pub fn resume_stream(ctx: Context<ResumeStream>, stream_id: String) -> Result<()> {
-        // Get Account
-        let stream_account = &mut ctx.accounts.stream;
-
-        let clock: Clock = Clock::get().unwrap();
-        let timestamp = clock.unix_timestamp as u64;
-
-        require!(
-            stream_account.resume_by != StateChangeAuth::Neither,
-            MyError::NotAuthorized
-        );
-        require!(
-            (stream_account.sender == ctx.accounts.authority.key()
-                && stream_account.resume_by != StateChangeAuth::OnlyReceiver)
-                || (stream_account.recipient == ctx.accounts.authority.key()
-                    && stream_account.resume_by != StateChangeAuth::OnlySender),
-            MyError::NotAuthorized
-        );
-        require!(
-            stream_account.stream_id == stream_id,
-            MyError::IncorrectStreamId
-        );
-        require!(
-            timestamp >= stream_account.start_time,
-            MyError::StreamNotStarted
-        );
-        require!(stream_account.is_paused == true, MyError::StreamNotPaused);
-
-        let stop = timestamp + stream_account.time_left;
-
-        stream_account.start_time = timestamp;
-        stream_account.stop_time = stop;
-        stream_account.is_paused = false;
+    pub fn cancel_stream(ctx: Context<CancelStream>, _stream_id: String) -> Result<()> {
+        let vault = AssetVault::native(
+            &ctx.accounts.stream,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.recipient.clone(),
+            treasury: ctx.accounts.treasury.clone(),
+            partner: ctx.accounts.partner.clone(),
+        };
 
-        Ok(())
+        engine::cancel(
+            &mut ctx.accounts.stream,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            &ctx.accounts.sender,
+            ctx.accounts.authority.key(),
+        )
     }
 
-    pub fn reload_stream(ctx: Context<ReloadStream>, stream_id: String, amount: u64) -> Result<()> {
-        // Get Account
-        let stream_account = &mut ctx.accounts.stream;
-        //   let clock: Clock = Clock::get().unwrap();
-        //   let timestamp = clock.unix_timestamp as u128;
-        //   let stop = stream_account.stop_time;
+    pub fn cancel_stream_token(ctx: Context<CancelStreamToken>, _stream_id: String) -> Result<()> {
+        let vault = AssetVault::token(
+            &ctx.accounts.stream,
+            &ctx.accounts.stream_tokens.to_account_info(),
+            &ctx.accounts.token_address.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.recipient_tokens.to_account_info(),
+            treasury: ctx
+                .accounts
+                .treasury_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+            partner: ctx
+                .accounts
+                .partner_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+        };
 
-        require!(
-            stream_account.is_infinite == true,
-            MyError::NotInfiniteStream
-        );
-        require!(
-            stream_account.stream_id == stream_id,
-            MyError::IncorrectStreamId
-        );
-        require!(amount > 0, MyError::DepositIsZero);
+        engine::cancel(
+            &mut ctx.accounts.stream,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            &ctx.accounts.sender_tokens.to_account_info(),
+            ctx.accounts.authority.key(),
+        )
+    }
 
-        require!(
-            stream_account.is_cancelled == false,
-            MyError::StreamAlreadyCancelled
-        );
+    pub fn pause_stream(ctx: Context<PauseStream>, _stream_id: String) -> Result<()> {
+        let vault = AssetVault::native(
+            &ctx.accounts.stream,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.recipient.clone(),
+            treasury: ctx.accounts.treasury.clone(),
+            partner: ctx.accounts.partner.clone(),
+        };
 
-        let rate = stream_account.rate_of_stream;
-        let interval = stream_account.interval;
+        engine::pause(
+            &mut ctx.accounts.stream,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            ctx.accounts.authority.key(),
+        )
+    }
 
-        /* Without this, the duration would be zero. */
-        require!(amount >= rate, MyError::DepositSmallerThanTime);
+    pub fn pause_stream_token(ctx: Context<PauseStreamToken>, _stream_id: String) -> Result<()> {
+        let vault = AssetVault::token(
+            &ctx.accounts.stream,
+            &ctx.accounts.stream_tokens.to_account_info(),
+            &ctx.accounts.token_address.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.recipient_tokens.to_account_info(),
+            treasury: ctx
+                .accounts
+                .treasury_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+            partner: ctx
+                .accounts
+                .partner_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+        };
 
-        let duration = ((amount as f64 / rate as f64) * interval as f64).round();
+        engine::pause(
+            &mut ctx.accounts.stream,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            ctx.accounts.authority.key(),
+        )
+    }
 
-        // if timestamp <= stop {
-        if stream_account.is_paused == true {
-            stream_account.time_left += duration as u64;
-        } else {
-            stream_account.stop_time += duration as u64;
-        }
-        /*      } else{
-            stream_account.start_time = timestamp;
-            stream_account.stop_time = timestamp + duration as u128;
-        } */
+    pub fn edit_stream(
+        ctx: Context<EditStream>,
+        _stream_id: String,
+        stream_title: String,
+        interval: u64,
+        rate: u64,
+        duration: u64,
+    ) -> Result<()> {
+        let vault = AssetVault::native(
+            &ctx.accounts.stream,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.recipient.clone(),
+            treasury: ctx.accounts.treasury.clone(),
+            partner: ctx.accounts.partner.clone(),
+        };
 
-        stream_account.remaining_balance += amount;
-        stream_account.deposit += amount;
+        engine::edit(
+            &mut ctx.accounts.stream,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            ctx.accounts.authority.key(),
+            stream_title,
+            interval,
+            rate,
+            duration,
+        )
+    }
 
-        let sender = ctx.accounts.sender.key();
+    pub fn edit_stream_token(
+        ctx: Context<EditStreamToken>,
+        _stream_id: String,
+        stream_title: String,
+        interval: u64,
+        rate: u64,
+        duration: u64,
+    ) -> Result<()> {
+        let vault = AssetVault::token(
+            &ctx.accounts.stream,
+            &ctx.accounts.stream_tokens.to_account_info(),
+            &ctx.accounts.token_address.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+        let payees = Payees {
+            recipient: ctx.accounts.recipient_tokens.to_account_info(),
+            treasury: ctx
+                .accounts
+                .treasury_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+            partner: ctx
+                .accounts
+                .partner_tokens
+                .as_ref()
+                .map(|t| t.to_account_info()),
+        };
 
-        let seeds = &[
-            stream_id.as_bytes(),
-            sender.as_ref(),
-            &[stream_account.bump],
-        ];
+        engine::edit(
+            &mut ctx.accounts.stream,
+            &ctx.accounts.config,
+            &vault,
+            &payees,
+            ctx.accounts.authority.key(),
+            stream_title,
+            interval,
+            rate,
+            duration,
+        )
+    }
 
-        let ix = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.sender.key(),
-            &stream_account.key(),
-            amount,
-        );
+    pub fn resume_stream(ctx: Context<ResumeStream>, _stream_id: String) -> Result<()> {
+        engine::resume(&mut ctx.accounts.stream, ctx.accounts.authority.key())
+    }
 
-        anchor_lang::solana_program::program::invoke_signed(
-            &ix,
-            &[
-                ctx.accounts.sender.to_account_info(),
-                stream_account.to_account_info(),
-            ],
-            &[&seeds[..]],
+    pub fn reload_stream(
+        ctx: Context<ReloadStream>,
+        _stream_id: String,
+        amount: u64,
+    ) -> Result<()> {
+        let vault = AssetVault::native(
+            &ctx.accounts.stream,
+            &ctx.accounts.system_program.to_account_info(),
         )?;
+        let sender = ctx.accounts.sender.to_account_info();
 
-        Ok(())
+        engine::reload(&mut ctx.accounts.stream, &vault, &sender, &sender, amount)
     }
 
     pub fn reload_stream_token(
         ctx: Context<ReloadStreamToken>,
-        stream_id: String,
+        _stream_id: String,
         amount: u64,
     ) -> Result<()> {
-        // Get Account
-        let stream_account = &mut ctx.accounts.stream;
-        //    let clock: Clock = Clock::get().unwrap();
-        //    let timestamp = clock.unix_timestamp as u128;
-        //    let stop = stream_account.stop_time;
-
-        require!(
-            stream_account.is_infinite == true,
-            MyError::NotInfiniteStream
-        );
-        require!(
-            stream_account.stream_id == stream_id,
-            MyError::IncorrectStreamId
-        );
-        require!(amount > 0, MyError::DepositIsZero);
-
-        require!(
-            stream_account.is_cancelled == false,
-            MyError::StreamAlreadyCancelled
-        );
-
-        let rate = stream_account.rate_of_stream;
-        let interval = stream_account.interval;
-
-        /* Without this, the duration would be zero. */
-        require!(amount >= rate, MyError::DepositSmallerThanTime);
-
-        let duration = ((amount as f64 / rate as f64) * interval as f64).round();
-
-        //   if timestamp <= stop {
-        if stream_account.is_paused == true {
-            stream_account.time_left += duration as u64;
-        } else {
-            stream_account.stop_time += duration as u64;
-        }
-        /*    } else{
-            stream_account.start_time = timestamp;
-            stream_account.stop_time = timestamp + duration as u128;
-        } */
-
-        stream_account.remaining_balance += amount;
-        stream_account.deposit += amount;
-
-        transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.sender_tokens.clone().to_account_info(),
-                    to: ctx.accounts.stream_tokens.to_account_info(),
-                    authority: ctx.accounts.sender.clone().to_account_info(),
-                },
-            ),
-            amount,
+        let vault = AssetVault::token(
+            &ctx.accounts.stream,
+            &ctx.accounts.stream_tokens.to_account_info(),
+            &ctx.accounts.token_address.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
         )?;
 
-        Ok(())
+        engine::reload(
+            &mut ctx.accounts.stream,
+            &vault,
+            &ctx.accounts.sender_tokens.to_account_info(),
+            &ctx.accounts.sender.to_account_info(),
+            amount,
+        )
     }
 
-    pub fn delete_stream(ctx: Context<DeleteStream>, stream_id: String) -> Result<()> {
-        // Get Account
-        let stream_account = &mut ctx.accounts.stream;
-
-        require!(
-            stream_account.stream_id == stream_id,
-            MyError::IncorrectStreamId
-        );
-        require!(
-            stream_account.remaining_balance == 0,
-            MyError::StreamNotEmpty
-        );
-        require!(
-            stream_account.sender == ctx.accounts.sender.key(),
-            MyError::NotAuthorized
-        );
-
-        stream_account.close(ctx.accounts.sender.to_account_info())?;
+    pub fn get_stream_status(ctx: Context<GetStreamStatus>) -> Result<()> {
+        let status = ctx.accounts.stream.status_at(engine::now()?)?;
+        set_return_data(&status.try_to_vec()?);
 
         Ok(())
     }
-}
-
-#[derive(Accounts)]
-#[instruction(stream_id: String)]
-pub struct CreateStream<'info> {
-    // stream_account Account PDA
-    #[account(
-        init,
-        seeds = [stream_id.as_bytes(), sender.key().as_ref()],
-        bump,
-        payer = sender,
-        space = 16 + StreamAccount::MAX_SIZE
-    )]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(mut)]
-    pub sender: Signer<'info>,
-    /// CHECK: safe
-    #[account(mut)]
-    pub recipient: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-#[instruction(stream_id: String)]
-pub struct CreateStreamToken<'info> {
-    // stream_account Account PDA
-    #[account(
-        init,
-        seeds = [stream_id.as_bytes(), sender.key().as_ref()],
-        bump,
-        payer = sender,
-        space = 16 + StreamAccount::MAX_SIZE
-    )]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(mut)]
-    pub sender: Signer<'info>,
-    /// CHECK: safe
-    #[account(mut)]
-    pub recipient: AccountInfo<'info>,
-    pub token_address: Account<'info, Mint>,
-    #[account(mut)]
-    pub sender_tokens: Box<Account<'info, TokenAccount>>,
-    #[account(init_if_needed,
-        payer = sender,
-        associated_token::mint = token_address,
-        associated_token::authority = stream)]
-    pub stream_tokens: Box<Account<'info, TokenAccount>>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub rent: Sysvar<'info, Rent>,
-}
-
-#[derive(Accounts)]
-pub struct WithdrawFromStream<'info> {
-    #[account(mut)]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    /// CHECK: safe
-    #[account(mut)]
-    pub recipient: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct WithdrawFromStreamToken<'info> {
-    #[account(mut)]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(
-        mut,
-        token::mint = token_address,
-        token::authority = stream
-    )]
-    pub stream_tokens: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    /// CHECK: safe
-    #[account(mut)]
-    pub sender: AccountInfo<'info>,
-    /// CHECK: safe
-    #[account(mut)]
-    pub recipient: AccountInfo<'info>,
-    #[account(init_if_needed,
-        payer = authority,
-        associated_token::mint = token_address,
-        associated_token::authority = recipient)]
-    pub recipient_tokens: Account<'info, TokenAccount>,
-    pub token_address: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub rent: Sysvar<'info, Rent>,
-}
-
-#[derive(Accounts)]
-pub struct CancelStream<'info> {
-    #[account(mut)]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    /// CHECK: safe
-    #[account(mut)]
-    pub sender: AccountInfo<'info>,
-    /// CHECK: safe
-    #[account(mut)]
-    pub recipient: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct CancelStreamToken<'info> {
-    #[account(mut)]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(
-        mut,
-        token::mint = token_address,
-        token::authority = stream
-    )]
-    pub stream_tokens: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    /// CHECK: safe
-    #[account(mut)]
-    pub recipient: AccountInfo<'info>,
-    #[account(init_if_needed,
-        payer = authority,
-        associated_token::mint = token_address,
-        associated_token::authority = recipient)]
-    pub recipient_tokens: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub sender_tokens: Box<Account<'info, TokenAccount>>,
-    pub token_address: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub rent: Sysvar<'info, Rent>,
-}
-
-#[derive(Accounts)]
-pub struct PauseStream<'info> {
-    #[account(mut)]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    /// CHECK: safe
-    #[account(mut)]
-    pub recipient: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct PauseStreamToken<'info> {
-    #[account(mut)]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(
-        mut,
-        token::mint = token_address,
-        token::authority = stream
-    )]
-    pub stream_tokens: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    /// CHECK: safe
-    #[account(mut)]
-    pub sender: AccountInfo<'info>,
-    /// CHECK: safe
-    #[account(mut)]
-    pub recipient: AccountInfo<'info>,
-    #[account(init_if_needed,
-        payer = authority,
-        associated_token::mint = token_address,
-        associated_token::authority = recipient)]
-    pub recipient_tokens: Account<'info, TokenAccount>,
-    pub token_address: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub rent: Sysvar<'info, Rent>,
-}
-
-#[derive(Accounts)]
-pub struct ResumeStream<'info> {
-    #[account(mut)]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct ReloadStream<'info> {
-    // stream_account Account PDA
-    #[account(mut)]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(mut)]
-    pub sender: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
 
-#[derive(Accounts)]
-pub struct ReloadStreamToken<'info> {
-    #[account(mut)]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(
-        mut,
-        token::mint = token_address,
-        token::authority = stream
-    )]
-    pub stream_tokens: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub sender: Signer<'info>,
-    #[account(mut)]
-    pub sender_tokens: Account<'info, TokenAccount>,
-    pub token_address: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-}
-
-#[derive(Accounts)]
-pub struct DeleteStream<'info> {
-    // stream_account Account PDA
-    #[account(mut)]
-    pub stream: Account<'info, StreamAccount>,
-    #[account(mut)]
-    pub sender: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
-
-#[account]
-pub struct StreamAccount {
-    // Stream Identifier
-    pub stream_id: String,
-    // Stream Title
-    pub stream_title: String,
-    // Recipient address
-    pub recipient: Pubkey,
-    // Sender address
-    pub sender: Pubkey,
-    // Token
-    pub token_address: Pubkey,
-    // Stream Creation time
-    pub create_time: u64,
-    // Stream start time
-    pub start_time: u64,
-    // Stream end time
-    pub stop_time: u64,
-    // Balance Remaining
-    pub remaining_balance: u64,
-    // Total Deposit
-    pub deposit: u64,
-    //Withdrawn Amount
-    pub withdrawn: u64,
-    // Cliff Amount
-    pub cliff_amount: u64,
-    // Interval of Stream
-    pub interval: u64,
-    // Rate per second
-    pub rate_of_stream: u64,
-    // Pause Timestamp
-    pub time_left: u64,
-    // Amount withdrawn when stream is paused
-    pub paused_amount: u64,
-    // Bump
-    pub bump: u8,
-    // Who can Cancel the Stream
-    pub cancel_by: StateChangeAuth,
-    // Who can Pause the Stream
-    pub pause_by: StateChangeAuth,
-    // Who can Resume the Stream
-    pub resume_by: StateChangeAuth,
-    // Who can Withdraw from the Stream,
-    pub withdraw_by: StateChangeAuth,
-    // Who can Edit the Stream,
-    pub edit_by: StateChangeAuth,
-    // Whether Cliff is in percentage or value
-    pub is_cliff_percent: bool,
-    // Status of Stream
-    pub is_paused: bool,
-    // Can this stream be deleted
-    pub is_cancelled: bool,
-    // Infinite Stream
-    pub is_infinite: bool,
-}
-
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
-pub enum StateChangeAuth {
-    OnlySender,
-    OnlyReceiver,
-    Both,
-    Neither,
-}
-
-impl StreamAccount {
-    pub const MAX_SIZE: usize = (4 + (6 * 4))
-        + (4 + (50 * 4))
-        + 32
-        + 32
-        + 32
-        + 8
-        + 8
-        + 8
-        + 8
-        + 8
-        + 8
-        + 8
-        + 8
-        + 8
-        + 8
-        + 8
-        + 1
-        + (1 + 1)
-        + (1 + 1)
-        + (1 + 1)
-        + (1 + 1)
-        + (1 + 1)
-        + 1
-        + 1
-        + 1
-        + 1;
-}
-
-#[error_code]
-pub enum MyError {
-    #[msg("Recipient cannot be same as Sender.")]
-    SenderIsRecipient,
-    #[msg("Deposit Amount is Zero")]
-    DepositIsZero,
-    #[msg("Interval of Streaming is Zero")]
-    IntervalIsZero,
-    #[msg("Amount is Zero")]
-    AmountIsZero,
-    #[msg("Start Time is before Block Timestamp.")]
-    PastStartTime,
-    #[msg("Start Time is after the Stop Time.")]
-    FutureStartTime,
-    #[msg("Deposit is smaller than the Time Delta")]
-    DepositSmallerThanTime,
-    #[msg("Deposit is not a Multiple of the Time Delta")]
-    DepositNotMultipleOfTime,
-    #[msg("The Stream ID is Incorrect")]
-    IncorrectStreamId,
-    #[msg("Stream Title cannot be longer than 50 characters.")]
-    TitleTooLong,
-    #[msg("Nothing To Withdraw as of now.")]
-    NothingToWithdraw,
-    #[msg("Incorrect Recipient Address")]
-    IncorrectRecipient,
-    #[msg("Incorrect Sender Address")]
-    IncorrectSender,
-    #[msg("Incorrect Token Address")]
-    IncorrectTokenAddress,
-    #[msg("Stream has not started yet.")]
-    StreamNotStarted,
-    #[msg("Stream is Paused. Resume the Stream to Withdraw.")]
-    StreamIsPaused,
-    #[msg("Stream is Already Paused.")]
-    StreamAlreadyPaused,
-    #[msg("Stream is not Paused.")]
-    StreamNotPaused,
-    #[msg("Stream has already Ended.")]
-    StreamAlreadyEnded,
-    #[msg("Stream is already Cancelled.")]
-    StreamAlreadyCancelled,
-    #[msg("Stream Not Empty. Withdraw Tokens completely and then try again.")]
-    StreamNotEmpty,
-    #[msg("This is not an infinite stream.")]
-    NotInfiniteStream,
-    #[msg("You are not Authorized to perform the desired operation. !!")]
-    NotAuthorized,
-    #[msg("Invalid Value for Cancel By Flag")]
-    InvalidCancelBy,
-    #[msg("Invalid Value for Pause By Flag")]
-    InvalidPauseBy,
-    #[msg("Invalid Value for Withdraw By Flag")]
-    InvalidWithdrawBy,
-    #[msg("Invalid Value for Resume By Flag")]
-    InvalidResumeBy,
-    #[msg("Invalid Value for Edit By Flag")]
-    InvalidEditBy,
-    #[msg("The Duration is incorrect. Please check the values of Amount, Rate, Interval and Duration.")]
-    IncorrectDuration,
-    #[msg("The Associated Token Account of Recipient is Incorrect.")]
-    AssociatedTokenAccountIncorrect,
+    pub fn delete_stream(ctx: Context<DeleteStream>, _stream_id: String) -> Result<()> {
+        engine::delete(
+            &mut ctx.accounts.stream,
+            &ctx.accounts.sender.to_account_info(),
+        )
+    }
 }
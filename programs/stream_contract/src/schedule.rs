@@ -0,0 +1,491 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::MyError;
+use crate::state::*;
+use crate::status::StreamStatus;
+
+// Vesting math of a stream. Everything here is a pure function of the account state and a
+// timestamp, so off-chain clients get the same answers as the instruction handlers.
+impl StreamAccount {
+    /// Amount accrued to the recipient at `timestamp` and not withdrawn yet.
+    ///
+    /// Does not check whether the stream is running or past its cliff date, nothing has
+    /// accrued before the start.
+    pub fn withdrawable_at(&self, timestamp: u64) -> Result<u64> {
+        if timestamp >= self.stop_time {
+            return Ok(self.remaining_balance);
+        }
+        if timestamp < self.start_time {
+            return Ok(0);
+        }
+
+        if self.schedule == ScheduleKind::Steps {
+            let unlocked = self
+                .unlocked_at(timestamp)
+                .checked_sub(self.withdrawn)
+                .ok_or(MyError::BalanceUnderflow)?;
+            return Ok(unlocked.min(self.remaining_balance));
+        }
+
+        let delta = timestamp - self.start_time;
+
+        // Vested before the last pause or edit, the cliff amount otherwise
+        let vested = match self.paused_amount {
+            0 => self.cliff_amount,
+            _ => self.paused_amount,
+        };
+
+        let accrued = match self.schedule {
+            ScheduleKind::Linear => {
+                // Whatever is left to vest is spread evenly over the seconds until stop_time
+                let streaming = self
+                    .withdrawn
+                    .checked_add(self.remaining_balance)
+                    .and_then(|total| total.checked_sub(vested))
+                    .ok_or(MyError::BalanceUnderflow)?;
+                let span = self.stop_time - self.start_time;
+                let streamed = streaming as u128 * delta as u128 / span as u128;
+                vested
+                    .checked_add(streamed as u64)
+                    .ok_or(MyError::MathOverflow)?
+            }
+            _ => (delta / self.interval)
+                .checked_mul(self.rate_of_stream)
+                .and_then(|streamed| streamed.checked_add(vested))
+                .ok_or(MyError::MathOverflow)?,
+        };
+        let ready_for_withdrawal = accrued
+            .checked_sub(self.withdrawn)
+            .ok_or(MyError::BalanceUnderflow)?;
+
+        // The balance can fall short of the schedule when less than the deposit arrived
+        Ok(ready_for_withdrawal.min(self.remaining_balance))
+    }
+
+    /// Amount unlocked by a step schedule at `timestamp`, withdrawn included.
+    pub fn unlocked_at(&self, timestamp: u64) -> u64 {
+        self.checkpoints
+            .iter()
+            .take_while(|c| c.timestamp <= timestamp)
+            .last()
+            .map_or(0, |c| c.cumulative_unlocked)
+    }
+
+    /// Amount the recipient keeps when the stream is cancelled at `timestamp`.
+    ///
+    /// Nothing has vested for the recipient before the start or the cliff date. A paused
+    /// stream was settled when it was paused, a split stream still holds that amount.
+    pub fn owed_on_cancel_at(&self, timestamp: u64) -> Result<u64> {
+        if timestamp < self.start_time {
+            return Ok(0);
+        }
+
+        if self.is_paused() {
+            return match self.cliff_time_left {
+                0 => Ok(self
+                    .paused_amount
+                    .checked_sub(self.withdrawn)
+                    .ok_or(MyError::BalanceUnderflow)?),
+                _ => Ok(0),
+            };
+        }
+
+        if timestamp < self.cliff_time {
+            return Ok(0);
+        }
+
+        self.withdrawable_at(timestamp)
+    }
+
+    /// Amount returned to the sender when the stream is cancelled at `timestamp`.
+    pub fn refundable_at(&self, timestamp: u64) -> Result<u64> {
+        if self.is_cancelled() {
+            return Ok(0);
+        }
+
+        Ok(self
+            .remaining_balance
+            .checked_sub(self.owed_on_cancel_at(timestamp)?)
+            .ok_or(MyError::BalanceUnderflow)?)
+    }
+
+    /// Balances and phase of the stream at `timestamp`, as returned by `get_stream_status`.
+    pub fn status_at(&self, timestamp: u64) -> Result<StreamStatusView> {
+        let phase = match self.state_at(timestamp) {
+            StreamStatus::Scheduled => StreamPhase::NotStarted,
+            StreamStatus::Active if timestamp < self.cliff_time => StreamPhase::Cliff,
+            StreamStatus::Active => StreamPhase::Streaming,
+            StreamStatus::Paused => StreamPhase::Paused,
+            StreamStatus::Depleted | StreamStatus::Completed => StreamPhase::Ended,
+            StreamStatus::Cancelled => StreamPhase::Cancelled,
+        };
+
+        // Accrued and still in the stream, locked until the cliff date
+        let accrued = match phase {
+            // Only a split stream keeps a balance, owed to its recipients
+            StreamPhase::Cancelled => self.remaining_balance,
+            StreamPhase::Paused => self
+                .paused_amount
+                .checked_sub(self.withdrawn)
+                .ok_or(MyError::BalanceUnderflow)?,
+            _ => self.withdrawable_at(timestamp)?,
+        };
+
+        let withdrawable = match phase {
+            StreamPhase::Cancelled | StreamPhase::Streaming | StreamPhase::Ended => accrued,
+            _ => 0,
+        };
+
+        Ok(StreamStatusView {
+            phase,
+            withdrawable,
+            vested: self
+                .withdrawn
+                .checked_add(accrued)
+                .ok_or(MyError::MathOverflow)?,
+            unvested: self
+                .remaining_balance
+                .checked_sub(accrued)
+                .ok_or(MyError::BalanceUnderflow)?,
+            refundable: self.refundable_at(timestamp)?,
+            withdrawn: self.withdrawn,
+            remaining_balance: self.remaining_balance,
+            timestamp,
+        })
+    }
+}
+
+/// Computes `a * b / d` in u128, rounded half up (`x.5` goes to `x + 1`).
+///
+/// This is the rounding rule for reloads, a reload of `amount` extends the stream by
+/// `mul_div_round(amount, interval, rate)` seconds.
+/// Returns `None` if `d` is zero or the result does not fit in a u64.
+pub fn mul_div_round(a: u64, b: u64, d: u64) -> Option<u64> {
+    if d == 0 {
+        return None;
+    }
+    let n = a as u128 * b as u128;
+    let d = d as u128;
+    let mut q = n / d;
+    if (n % d) * 2 >= d {
+        q += 1;
+    }
+    u64::try_from(q).ok()
+}
+
+/// Seconds an interval schedule takes to pay out `amount` at `rate` every `interval`, the
+/// last interval paying what is left.
+///
+/// The duration of an interval stream must equal this, so that it stops the moment
+/// everything has vested. Returns `None` if `rate` is zero or the result does not fit in
+/// a u64.
+pub fn interval_duration(amount: u64, interval: u64, rate: u64) -> Option<u64> {
+    let last = amount.checked_rem(rate)?;
+    let intervals = amount / rate + u64::from(last > 0);
+    intervals.checked_mul(interval)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::engine::{build_step_stream_account, build_stream_account};
+    use proptest::prelude::*;
+
+    const NOW: u64 = 1_000_000;
+
+    // Starts at creation, shared with the tests of the other modules
+    pub(crate) fn params(
+        amount: u64,
+        interval: u64,
+        rate: u64,
+        duration: u64,
+    ) -> CreateStreamParams {
+        CreateStreamParams {
+            stream_id: "stream".to_string(),
+            stream_title: "title".to_string(),
+            amount,
+            cliff_amount: 0,
+            is_cliff_percent: false,
+            start: 0,
+            cliff_time: 0,
+            interval,
+            rate,
+            duration,
+            is_infinite: false,
+            cancel_by: 0,
+            pause_by: 0,
+            resume_by: 0,
+            withdraw_by: 1,
+            edit_by: 0,
+            start_now: true,
+            partner: None,
+            partner_fee_bps: 0,
+            is_linear: false,
+        }
+    }
+
+    fn stream(params: &CreateStreamParams) -> StreamAccount {
+        build_stream_account(
+            params,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::default(),
+            255,
+            NOW,
+        )
+        .unwrap()
+    }
+
+    fn step_stream(checkpoints: &[(u64, u64)]) -> StreamAccount {
+        let amount = checkpoints.last().unwrap().1;
+        build_step_stream_account(
+            &params(amount, 0, 0, 0),
+            checkpoints
+                .iter()
+                .map(|&(offset, unlocked)| Checkpoint {
+                    timestamp: NOW + offset,
+                    cumulative_unlocked: unlocked,
+                })
+                .collect(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::default(),
+            255,
+            NOW,
+        )
+        .unwrap()
+    }
+
+    // Records a withdrawal the way the engine does, without the transfer
+    fn withdraw(stream: &mut StreamAccount, amount: u64) {
+        stream.withdrawn += amount;
+        stream.remaining_balance -= amount;
+    }
+
+    #[test]
+    fn interval_stream_accrues_whole_intervals() {
+        let stream = stream(&params(1_000, 10, 100, 100));
+
+        assert_eq!(stream.withdrawable_at(NOW - 1).unwrap(), 0);
+        assert_eq!(stream.withdrawable_at(NOW).unwrap(), 0);
+        assert_eq!(stream.withdrawable_at(NOW + 9).unwrap(), 0);
+        assert_eq!(stream.withdrawable_at(NOW + 25).unwrap(), 200);
+        assert_eq!(stream.withdrawable_at(NOW + 100).unwrap(), 1_000);
+        assert_eq!(stream.withdrawable_at(u64::MAX).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn cliff_amount_is_vested_at_the_cliff_date() {
+        let mut p = params(1_000, 10, 100, 80);
+        p.cliff_amount = 2_000;
+        p.is_cliff_percent = true;
+        p.cliff_time = NOW + 30;
+        let stream = stream(&p);
+
+        assert_eq!(stream.cliff_amount, 200);
+        assert_eq!(stream.withdrawable_at(NOW).unwrap(), 200);
+        assert_eq!(stream.withdrawable_at(NOW + 35).unwrap(), 500);
+
+        // Locked until the cliff date, so cancelling before it refunds everything
+        assert_eq!(stream.owed_on_cancel_at(NOW + 29).unwrap(), 0);
+        assert_eq!(stream.refundable_at(NOW + 29).unwrap(), 1_000);
+        assert_eq!(stream.owed_on_cancel_at(NOW + 30).unwrap(), 500);
+        assert_eq!(stream.refundable_at(NOW + 30).unwrap(), 500);
+    }
+
+    #[test]
+    fn linear_stream_accrues_every_second() {
+        let mut p = params(1_000, 10, 100, 100);
+        p.is_linear = true;
+        let stream = stream(&p);
+
+        assert_eq!(stream.schedule, ScheduleKind::Linear);
+        assert_eq!(stream.withdrawable_at(NOW + 1).unwrap(), 10);
+        assert_eq!(stream.withdrawable_at(NOW + 25).unwrap(), 250);
+        assert_eq!(stream.withdrawable_at(NOW + 99).unwrap(), 990);
+        assert_eq!(stream.withdrawable_at(NOW + 100).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn linear_stream_stops_after_its_duration() {
+        // 1_050 at 100 every 10 seconds would take eleven intervals, 110 seconds
+        let mut p = params(1_050, 10, 100, 105);
+        p.is_linear = true;
+        let stream = stream(&p);
+
+        assert_eq!(stream.stop_time, NOW + 105);
+        assert_eq!(stream.withdrawable_at(NOW + 42).unwrap(), 420);
+        assert_eq!(stream.withdrawable_at(NOW + 104).unwrap(), 1_040);
+        assert_eq!(stream.withdrawable_at(NOW + 105).unwrap(), 1_050);
+    }
+
+    #[test]
+    fn step_stream_unlocks_at_checkpoints() {
+        let stream = step_stream(&[(0, 100), (30, 400), (60, 1_000)]);
+
+        assert_eq!(stream.stop_time, NOW + 60);
+        assert_eq!(stream.withdrawable_at(NOW).unwrap(), 100);
+        assert_eq!(stream.withdrawable_at(NOW + 29).unwrap(), 100);
+        assert_eq!(stream.withdrawable_at(NOW + 30).unwrap(), 400);
+        assert_eq!(stream.withdrawable_at(NOW + 60).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn withdrawals_are_not_accrued_twice() {
+        let mut stream = stream(&params(1_000, 10, 100, 100));
+
+        withdraw(&mut stream, 150);
+        assert_eq!(stream.withdrawable_at(NOW + 25).unwrap(), 50);
+        assert_eq!(stream.withdrawable_at(NOW + 100).unwrap(), 850);
+    }
+
+    #[test]
+    fn status_follows_the_phases() {
+        let mut p = params(1_000, 10, 100, 100);
+        p.start_now = false;
+        p.start = NOW + 10;
+        p.cliff_time = NOW + 30;
+        let mut stream = stream(&p);
+
+        let status = stream.status_at(NOW).unwrap();
+        assert_eq!(status.phase, StreamPhase::NotStarted);
+        assert_eq!(status.refundable, 1_000);
+
+        let status = stream.status_at(NOW + 25).unwrap();
+        assert_eq!(status.phase, StreamPhase::Cliff);
+        assert_eq!(status.withdrawable, 0);
+        assert_eq!(status.vested, 100);
+        assert_eq!(status.refundable, 1_000);
+
+        let status = stream.status_at(NOW + 45).unwrap();
+        assert_eq!(status.phase, StreamPhase::Streaming);
+        assert_eq!(status.withdrawable, 300);
+        assert_eq!(status.unvested, 700);
+        assert_eq!(status.refundable, 700);
+
+        let status = stream.status_at(NOW + 110).unwrap();
+        assert_eq!(status.phase, StreamPhase::Ended);
+        assert_eq!(status.withdrawable, 1_000);
+        assert_eq!(status.refundable, 0);
+
+        stream.set_status(StreamStatus::Cancelled);
+        stream.remaining_balance = 0;
+        assert_eq!(
+            stream.status_at(NOW + 45).unwrap().phase,
+            StreamPhase::Cancelled
+        );
+    }
+
+    #[test]
+    fn amounts_beyond_f64_precision_vest_exactly() {
+        // 2^55 + 1 is the first of many u64 an f64 rounds to a neighbour
+        let rate = (1 << 55) + 1;
+        let interval = stream(&params(3 * rate, 10, rate, 30));
+        assert_eq!(interval.withdrawable_at(NOW + 25).unwrap(), 2 * rate);
+
+        let amount = (1 << 60) + 3;
+        let mut p = params(amount, 1, mul_div_round(amount, 1, 7).unwrap(), 7);
+        p.is_linear = true;
+        let linear = stream(&p);
+        let expected = (amount as u128 * 3 / 7) as u64;
+        assert_eq!(linear.withdrawable_at(NOW + 3).unwrap(), expected);
+    }
+
+    #[test]
+    fn mul_div_round_rounds_half_up() {
+        assert_eq!(mul_div_round(10, 1, 4), Some(3));
+        assert_eq!(mul_div_round(9, 1, 4), Some(2));
+        assert_eq!(mul_div_round(1, 1, 0), None);
+        assert_eq!(mul_div_round(u64::MAX, 2, 1), None);
+    }
+
+    #[test]
+    fn interval_duration_ends_with_the_last_interval() {
+        assert_eq!(interval_duration(1_000, 10, 100), Some(100));
+        assert_eq!(interval_duration(1_050, 10, 100), Some(110));
+        assert_eq!(interval_duration(1, 1, 0), None);
+        assert_eq!(interval_duration(u64::MAX, 2, 1), None);
+    }
+
+    #[test]
+    fn amounts_beyond_f64_precision_divide_exactly() {
+        // 2^55 + 1 is the first of many u64 an f64 rounds to a neighbour
+        let rate = (1 << 55) + 1;
+        assert_eq!(mul_div_round(3 * rate, 10, 30), Some(rate));
+
+        let part = (1 << 57) + 1;
+        assert_eq!(mul_div_round(7 * part, 3, 7), Some(3 * part));
+    }
+
+    // Streams whose deposit is a whole number of intervals
+    fn any_stream() -> impl Strategy<Value = StreamAccount> {
+        (1u64..1_000, 1u64..1_000_000, 1u64..100_000, any::<bool>()).prop_map(
+            |(intervals, rate, interval, is_linear)| {
+                let mut p = params(intervals * rate, interval, rate, intervals * interval);
+                p.is_linear = is_linear;
+                stream(&p)
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn withdrawable_never_decreases(stream in any_stream(), a in 0u64..200_000_000, b in 0u64..200_000_000) {
+            let (early, late) = (NOW + a.min(b), NOW + a.max(b));
+            prop_assert!(stream.withdrawable_at(early)? <= stream.withdrawable_at(late)?);
+        }
+
+        #[test]
+        fn withdrawable_is_bounded_by_the_balance(stream in any_stream(), t in 0u64..200_000_000, paid in 0u64..=100) {
+            let mut stream = stream;
+            let before = stream.withdrawable_at(NOW + t)?;
+            let amount = before * paid / 100;
+            withdraw(&mut stream, amount);
+
+            let after = stream.withdrawable_at(NOW + t)?;
+            prop_assert_eq!(after, before - amount);
+            prop_assert!(after <= stream.remaining_balance);
+            prop_assert_eq!(stream.withdrawable_at(stream.stop_time)?, stream.remaining_balance);
+        }
+
+        #[test]
+        fn cancel_splits_the_whole_balance(stream in any_stream(), t in 0u64..200_000_000) {
+            let owed = stream.owed_on_cancel_at(NOW + t)?;
+            let refund = stream.refundable_at(NOW + t)?;
+            prop_assert_eq!(owed + refund, stream.remaining_balance);
+
+            let status = stream.status_at(NOW + t)?;
+            prop_assert_eq!(status.vested + status.unvested, stream.deposit);
+        }
+
+        #[test]
+        fn linear_is_never_behind_interval(intervals in 1u64..1_000, rate in 1u64..1_000_000, interval in 1u64..100_000, t in 0u64..200_000_000) {
+            let mut p = params(intervals * rate, interval, rate, intervals * interval);
+            let stepped = stream(&p);
+            p.is_linear = true;
+            let linear = stream(&p);
+
+            prop_assert!(linear.withdrawable_at(NOW + t)? >= stepped.withdrawable_at(NOW + t)?);
+        }
+
+        #[test]
+        fn split_shares_add_up_once_final(weights in prop::collection::vec(1u64..1_000, 2..20), vested in 0u64..u64::MAX / 2) {
+            let total: u64 = weights.iter().sum();
+            let mut shares: Vec<SplitShare> = weights
+                .iter()
+                .map(|w| SplitShare { recipient: Pubkey::new_unique(), weight_bps: w * 10_000 / total, withdrawn: 0 })
+                .collect();
+            let rest = 10_000 - shares.iter().map(|s| s.weight_bps).sum::<u64>();
+            shares[0].weight_bps += rest;
+            let split = SplitAccount { stream: Pubkey::new_unique(), shares, bump: 255 };
+
+            let running: u64 = (0..split.shares.len()).map(|i| split.entitlement(i, vested, false)).sum();
+            let last: u64 = (0..split.shares.len()).map(|i| split.entitlement(i, vested, true)).sum();
+            prop_assert!(running <= vested);
+            prop_assert_eq!(last, vested);
+            for i in 0..split.shares.len() {
+                prop_assert!(split.entitlement(i, vested, true) >= split.entitlement(i, vested, false));
+            }
+        }
+    }
+}
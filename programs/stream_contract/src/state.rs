@@ -0,0 +1,388 @@
+use anchor_lang::prelude::*;
+
+use crate::MAX_BPS;
+
+#[account]
+#[derive(Debug)]
+pub struct StreamAccount {
+    // Recipient address, first so that clients can filter on it
+    pub recipient: Pubkey,
+    // Sender address
+    pub sender: Pubkey,
+    // Stream Identifier
+    pub stream_id: String,
+    // Stream Title
+    pub stream_title: String,
+    // Token, default for native streams
+    pub token_address: Pubkey,
+    // Stream Creation time
+    pub create_time: u64,
+    // Stream start time
+    pub start_time: u64,
+    // Stream end time
+    pub stop_time: u64,
+    // Balance Remaining
+    pub remaining_balance: u64,
+    // Total Deposit
+    pub deposit: u64,
+    //Withdrawn Amount
+    pub withdrawn: u64,
+    // Cliff Amount
+    pub cliff_amount: u64,
+    // Nothing is withdrawable before this time
+    pub cliff_time: u64,
+    // Time left until the cliff when stream is paused
+    pub cliff_time_left: u64,
+    // Interval of Stream
+    pub interval: u64,
+    // Rate per second
+    pub rate_of_stream: u64,
+    // Pause Timestamp
+    pub time_left: u64,
+    // Amount withdrawn when stream is paused
+    pub paused_amount: u64,
+    // Protocol fee taken from the payouts, part of withdrawn
+    pub protocol_fee_paid: u64,
+    // Integrator that originated the stream, default when none
+    pub partner: Pubkey,
+    // Partner fee in basis points of every payout to the recipient
+    pub partner_fee_bps: u64,
+    // Partner fee taken from the payouts, part of withdrawn
+    pub partner_fee_paid: u64,
+    // Bump
+    pub bump: u8,
+    // Who can Cancel the Stream
+    pub cancel_by: StateChangeAuth,
+    // Who can Pause the Stream
+    pub pause_by: StateChangeAuth,
+    // Who can Resume the Stream
+    pub resume_by: StateChangeAuth,
+    // Who can Withdraw from the Stream,
+    pub withdraw_by: StateChangeAuth,
+    // Who can Edit the Stream,
+    pub edit_by: StateChangeAuth,
+    // Whether Cliff was given in percentage or value. cliff_amount is always absolute.
+    pub is_cliff_percent: bool,
+    // Paused or cancelled, the other statuses follow from the clock
+    pub run_state: RunState,
+    // Infinite Stream
+    pub is_infinite: bool,
+    // Paid out through a SplitAccount, which is the recipient
+    pub is_split: bool,
+    // How the deposit vests
+    pub schedule: ScheduleKind,
+    // Unlocks of a step schedule, empty otherwise
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+#[account]
+pub struct ProgramConfig {
+    // Admin allowed to update the config
+    pub admin: Pubkey,
+    // Admin proposed by transfer_admin, default when none
+    pub pending_admin: Pubkey,
+    // Emergency switch, stops every stream instruction
+    pub is_halted: bool,
+    // Mints allowed for token streams, empty allows every mint
+    pub allowed_mints: Vec<Pubkey>,
+    // Protocol fee in basis points of every payout to a recipient
+    pub fee_bps: u64,
+    // Receives the protocol fee, lamports or its associated token account
+    pub treasury: Pubkey,
+    // Bump
+    pub bump: u8,
+}
+
+impl ProgramConfig {
+    pub const MAX_MINTS: usize = 20;
+
+    // Hard cap on the protocol fee, 10%
+    pub const MAX_FEE_BPS: u64 = 1_000;
+
+    pub const MAX_SIZE: usize = 32 + 32 + 1 + (4 + (32 * Self::MAX_MINTS)) + 8 + 32 + 1;
+
+    pub fn is_mint_allowed(&self, mint: &Pubkey) -> bool {
+        self.allowed_mints.is_empty() || self.allowed_mints.contains(mint)
+    }
+
+    // Part of a payout kept as protocol fee, rounded down
+    pub fn protocol_fee(&self, amount: u64) -> u64 {
+        (amount as u128 * self.fee_bps as u128 / MAX_BPS as u128) as u64
+    }
+
+    // Treasury the payout instructions need, none while no fee is charged
+    pub fn fee_treasury(&self) -> Option<Pubkey> {
+        (self.fee_bps > 0).then_some(self.treasury)
+    }
+}
+
+#[account]
+#[derive(Debug)]
+pub struct SplitAccount {
+    // Stream whose payouts are split
+    pub stream: Pubkey,
+    // Recipients and their weights, adding up to 10000 basis points
+    pub shares: Vec<SplitShare>,
+    // Bump
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SplitShare {
+    pub recipient: Pubkey,
+    pub weight_bps: u64,
+    // Paid to this recipient, fees included
+    pub withdrawn: u64,
+}
+
+// Argument of create_split_stream and create_split_stream_token
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SplitRecipient {
+    pub recipient: Pubkey,
+    pub weight_bps: u64,
+}
+
+impl SplitAccount {
+    pub const MIN_SHARES: usize = 2;
+    pub const MAX_SHARES: usize = 20;
+
+    pub const MAX_SIZE: usize = 32 + (4 + ((32 + 8 + 8) * Self::MAX_SHARES)) + 1;
+
+    pub fn position(&self, recipient: &Pubkey) -> Option<usize> {
+        self.shares.iter().position(|s| s.recipient == *recipient)
+    }
+
+    // Part of `vested` owed to the share at `index`, rounded down while the stream runs.
+    // Once nothing more can vest the rounding dust is spread so that the shares add up
+    // to `vested` exactly, which never gives a share less than before.
+    pub fn entitlement(&self, index: usize, vested: u64, is_final: bool) -> u64 {
+        let part = |weight: u64| (vested as u128 * weight as u128 / MAX_BPS as u128) as u64;
+
+        if !is_final {
+            return part(self.shares[index].weight_bps);
+        }
+
+        let before: u64 = self.shares[..index].iter().map(|s| s.weight_bps).sum();
+        part(before + self.shares[index].weight_bps) - part(before)
+    }
+}
+
+// Arguments of create_stream and create_stream_token, one per stream in create_streams_batch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CreateStreamParams {
+    pub stream_id: String,
+    pub stream_title: String,
+    pub amount: u64,
+    pub cliff_amount: u64,
+    pub is_cliff_percent: bool,
+    pub start: u64,
+    pub cliff_time: u64,
+    pub interval: u64,
+    pub rate: u64,
+    pub duration: u64,
+    pub is_infinite: bool,
+    pub cancel_by: u8,
+    pub pause_by: u8,
+    pub resume_by: u8,
+    pub withdraw_by: u8,
+    pub edit_by: u8,
+    pub start_now: bool,
+    pub partner: Option<Pubkey>,
+    pub partner_fee_bps: u64,
+    // Vest every second instead of every interval
+    pub is_linear: bool,
+}
+
+// Per-stream result of withdraw_from_streams_batch, returned as return data
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BatchWithdrawal {
+    pub stream: Pubkey,
+    // Zero when the stream was skipped
+    pub amount: u64,
+    // Part of amount paid to the treasury
+    pub protocol_fee: u64,
+    // Part of amount paid to the partner
+    pub partner_fee: u64,
+    // Part of amount withheld by the mint's transfer fee
+    pub transfer_fee: u64,
+}
+
+// Returned by get_stream_status
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StreamStatusView {
+    pub phase: StreamPhase,
+    // Withdrawable now, by all recipients together for a split stream
+    pub withdrawable: u64,
+    // Accrued so far, withdrawn included, even if locked by the cliff or a pause
+    pub vested: u64,
+    // Left in the stream and not vested yet
+    pub unvested: u64,
+    // Returned to the sender if the stream is cancelled now
+    pub refundable: u64,
+    pub withdrawn: u64,
+    pub remaining_balance: u64,
+    // Clock the view was computed for
+    pub timestamp: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StreamPhase {
+    NotStarted,
+    // Started, nothing withdrawable before the cliff date
+    Cliff,
+    Streaming,
+    Paused,
+    // Past stop_time, everything left is withdrawable
+    Ended,
+    Cancelled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RunState {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScheduleKind {
+    // cliff_amount, then rate_of_stream every interval
+    Interval,
+    // cumulative_unlocked of the last checkpoint reached
+    Steps,
+    // cliff_amount, then the rest in proportion to the seconds elapsed until stop_time
+    Linear,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Checkpoint {
+    pub timestamp: u64,
+    // Total unlocked from the start of the stream until timestamp
+    pub cumulative_unlocked: u64,
+}
+
+impl Checkpoint {
+    pub const SIZE: usize = 8 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum StateChangeAuth {
+    OnlySender,
+    OnlyReceiver,
+    Both,
+    Neither,
+}
+
+impl StreamAccount {
+    // Hard cap on the partner fee, 10%
+    pub const MAX_PARTNER_FEE_BPS: u64 = 1_000;
+
+    pub const MAX_CHECKPOINTS: usize = 64;
+
+    // Offsets of the recipient and the sender in the account data, discriminator included
+    pub const RECIPIENT_OFFSET: usize = 8;
+    pub const SENDER_OFFSET: usize = 8 + 32;
+
+    pub const MAX_SIZE: usize = (4 + (6 * 4))
+        + (4 + (50 * 4))
+        + 32
+        + 32
+        + 32
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 32
+        + 8
+        + 8
+        + 1
+        + (1 + 1)
+        + (1 + 1)
+        + (1 + 1)
+        + (1 + 1)
+        + (1 + 1)
+        + 1
+        + 1
+        + 1
+        + 1
+        + 1
+        + 4;
+
+    // Part of a payout kept as partner fee, rounded down
+    pub fn partner_fee(&self, amount: u64) -> u64 {
+        (amount as u128 * self.partner_fee_bps as u128 / MAX_BPS as u128) as u64
+    }
+
+    // Native streams hold lamports and have no mint
+    pub fn is_native(&self) -> bool {
+        self.token_address == Pubkey::default()
+    }
+
+    // Whether `actor` may perform an operation restricted to `auth`
+    pub fn is_allowed(&self, auth: &StateChangeAuth, actor: &Pubkey) -> bool {
+        match auth {
+            StateChangeAuth::OnlySender => *actor == self.sender,
+            StateChangeAuth::OnlyReceiver => *actor == self.recipient,
+            StateChangeAuth::Both => *actor == self.sender || *actor == self.recipient,
+            StateChangeAuth::Neither => false,
+        }
+    }
+}
+
+impl StateChangeAuth {
+    pub fn from_flag(flag: u8) -> Self {
+        match flag {
+            0 => StateChangeAuth::OnlySender,
+            1 => StateChangeAuth::OnlyReceiver,
+            2 => StateChangeAuth::Both,
+            _ => StateChangeAuth::Neither,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(weights: &[u64]) -> SplitAccount {
+        SplitAccount {
+            stream: Pubkey::new_unique(),
+            shares: weights
+                .iter()
+                .map(|&weight_bps| SplitShare {
+                    recipient: Pubkey::new_unique(),
+                    weight_bps,
+                    withdrawn: 0,
+                })
+                .collect(),
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn final_entitlements_add_up_to_the_vested_amount() {
+        let thirds = split(&[3_333, 3_333, 3_334]);
+        let running: Vec<u64> = (0..3).map(|i| thirds.entitlement(i, 100, false)).collect();
+        let fin: Vec<u64> = (0..3).map(|i| thirds.entitlement(i, 100, true)).collect();
+
+        assert_eq!(running, [33, 33, 33]);
+        assert_eq!(fin, [33, 33, 34]);
+        assert!(running.iter().zip(&fin).all(|(r, f)| r <= f));
+
+        // The dust is spread, not all left to the last share
+        let halves = split(&[5_000, 2_500, 2_500]);
+        let fin: Vec<u64> = (0..3).map(|i| halves.entitlement(i, 3, true)).collect();
+        assert_eq!(halves.entitlement(1, 3, false), 0);
+        assert_eq!(fin, [1, 1, 1]);
+    }
+}
@@ -0,0 +1,241 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::MyError;
+use crate::state::*;
+
+// Lifecycle of a stream. Only pauses and cancels are stored, in run_state, the other
+// statuses follow from the clock. Every instruction asks `transition` whether it
+// may act on the stream and records the outcome with `set_status`.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StreamStatus {
+    // Before start_time
+    Scheduled,
+    // Between start_time and stop_time, possibly before the cliff date
+    Active,
+    Paused,
+    // An infinite stream past its stop_time, a reload starts it again
+    Depleted,
+    // A stream past its stop_time, everything left is withdrawable
+    Completed,
+    Cancelled,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StreamAction {
+    Withdraw,
+    Pause,
+    Resume,
+    Cancel,
+    Edit,
+    Reload,
+    Delete,
+}
+
+impl StreamAccount {
+    /// Where the stream is in its lifecycle at `timestamp`.
+    pub fn state_at(&self, timestamp: u64) -> StreamStatus {
+        if self.is_cancelled() {
+            StreamStatus::Cancelled
+        } else if self.is_paused() {
+            StreamStatus::Paused
+        } else if timestamp < self.start_time {
+            StreamStatus::Scheduled
+        } else if timestamp < self.stop_time {
+            StreamStatus::Active
+        } else if self.is_infinite {
+            StreamStatus::Depleted
+        } else {
+            StreamStatus::Completed
+        }
+    }
+
+    /// Status the stream moves to when `action` is taken at `timestamp`.
+    ///
+    /// Actions that leave a stream running return the status it is in now, the clock moves
+    /// it on from there. Combinations without a more specific error fail with
+    /// `InvalidStateTransition`.
+    pub fn transition(&self, action: StreamAction, timestamp: u64) -> Result<StreamStatus> {
+        use StreamAction::*;
+        use StreamStatus::*;
+
+        let status = self.state_at(timestamp);
+        let next = match (status, action) {
+            (Active | Depleted | Completed, Withdraw) => status,
+            // What vested before the cancel stays in a split stream for its recipients
+            (Cancelled, Withdraw) if self.is_split => status,
+            (Scheduled, Withdraw | Pause | Resume) => return err!(MyError::StreamNotStarted),
+            (Paused, Withdraw) => return err!(MyError::StreamIsPaused),
+
+            (Active, Pause) => Paused,
+            (Paused, Pause) => return err!(MyError::StreamAlreadyPaused),
+            (Depleted | Completed, Pause | Edit) => return err!(MyError::StreamAlreadyEnded),
+
+            (Paused, Resume) => Active,
+            (Active | Depleted | Completed, Resume) => return err!(MyError::StreamNotPaused),
+
+            (Scheduled | Active | Paused | Depleted | Completed, Cancel) => Cancelled,
+
+            (Scheduled | Active | Paused, Edit) => status,
+            (Scheduled | Active | Paused | Depleted, Reload) => status,
+
+            // Only the balance decides, a stream can be emptied before its stop_time
+            (_, Delete) if self.remaining_balance == 0 => status,
+            (_, Delete) => return err!(MyError::StreamNotEmpty),
+
+            (Cancelled, _) => return err!(MyError::StreamAlreadyCancelled),
+            _ => return err!(MyError::InvalidStateTransition),
+        };
+
+        Ok(next)
+    }
+
+    /// Records a status returned by `transition`.
+    pub fn set_status(&mut self, status: StreamStatus) {
+        self.run_state = match status {
+            StreamStatus::Paused => RunState::Paused,
+            StreamStatus::Cancelled => RunState::Cancelled,
+            _ => RunState::Running,
+        };
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.run_state == RunState::Paused
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.run_state == RunState::Cancelled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::build_stream_account;
+    use crate::schedule::tests::params;
+    use StreamAction::*;
+    use StreamStatus::*;
+
+    const START: u64 = 1_000;
+    const STOP: u64 = 2_000;
+
+    fn stream() -> StreamAccount {
+        let mut params = params(1_000, 1, 1, STOP - START);
+        params.start = START;
+        params.start_now = false;
+        build_stream_account(
+            &params,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::default(),
+            255,
+            START - 10,
+        )
+        .unwrap()
+    }
+
+    fn error(result: Result<StreamStatus>) -> String {
+        match result.unwrap_err() {
+            Error::AnchorError(e) => e.error_name,
+            e => panic!("{e:?}"),
+        }
+    }
+
+    #[test]
+    fn status_follows_the_clock_and_the_flags() {
+        let mut stream = stream();
+        assert_eq!(stream.state_at(START - 1), Scheduled);
+        assert_eq!(stream.state_at(START), Active);
+        assert_eq!(stream.state_at(STOP), Completed);
+
+        stream.is_infinite = true;
+        assert_eq!(stream.state_at(STOP), Depleted);
+
+        stream.set_status(Paused);
+        assert_eq!(stream.state_at(STOP), Paused);
+
+        stream.set_status(Cancelled);
+        assert!(!stream.is_paused());
+        assert_eq!(stream.state_at(START - 1), Cancelled);
+    }
+
+    #[test]
+    fn running_streams_pause_resume_and_cancel() {
+        let mut stream = stream();
+        assert_eq!(stream.transition(Pause, START).unwrap(), Paused);
+        assert_eq!(stream.transition(Withdraw, START).unwrap(), Active);
+        assert_eq!(stream.transition(Cancel, START - 1).unwrap(), Cancelled);
+
+        stream.set_status(Paused);
+        assert_eq!(stream.transition(Resume, STOP).unwrap(), Active);
+        assert_eq!(stream.transition(Edit, STOP).unwrap(), Paused);
+        assert_eq!(stream.transition(Cancel, STOP).unwrap(), Cancelled);
+    }
+
+    #[test]
+    fn cancelled_streams_only_pay_out_splits() {
+        let mut stream = stream();
+        stream.set_status(Cancelled);
+
+        for action in [Withdraw, Pause, Resume, Cancel, Edit, Reload] {
+            assert_eq!(
+                error(stream.transition(action, START)),
+                "StreamAlreadyCancelled"
+            );
+        }
+
+        stream.is_split = true;
+        assert_eq!(stream.transition(Withdraw, START).unwrap(), Cancelled);
+    }
+
+    #[test]
+    fn ended_streams_can_only_be_drained_or_reloaded() {
+        let mut stream = stream();
+        assert_eq!(error(stream.transition(Pause, STOP)), "StreamAlreadyEnded");
+        assert_eq!(error(stream.transition(Edit, STOP)), "StreamAlreadyEnded");
+        assert_eq!(error(stream.transition(Resume, STOP)), "StreamNotPaused");
+        assert_eq!(
+            error(stream.transition(Reload, STOP)),
+            "InvalidStateTransition"
+        );
+
+        stream.is_infinite = true;
+        assert_eq!(stream.transition(Reload, STOP).unwrap(), Depleted);
+    }
+
+    #[test]
+    fn unknown_transitions_are_rejected() {
+        let mut stream = stream();
+        assert_eq!(
+            error(stream.transition(Withdraw, START - 1)),
+            "StreamNotStarted"
+        );
+
+        stream.set_status(Paused);
+        assert_eq!(
+            error(stream.transition(Pause, START)),
+            "StreamAlreadyPaused"
+        );
+        assert_eq!(error(stream.transition(Withdraw, START)), "StreamIsPaused");
+    }
+
+    #[test]
+    fn only_emptied_streams_are_deleted() {
+        for (timestamp, status) in [
+            (START, Active),
+            (START, Paused),
+            (START, Cancelled),
+            (STOP, Completed),
+        ] {
+            let mut stream = stream();
+            stream.set_status(status);
+            assert_eq!(
+                error(stream.transition(Delete, timestamp)),
+                "StreamNotEmpty"
+            );
+
+            stream.remaining_balance = 0;
+            assert_eq!(stream.transition(Delete, timestamp).unwrap(), status);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token_interface::{self, TransferChecked};
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+
+use crate::errors::MyError;
+use crate::state::StreamAccount;
+
+/// Where the funds of a stream are held and how they move in and out.
+///
+/// Native streams keep their lamports on the stream account itself, token streams keep
+/// their tokens in the associated token account of the stream PDA. Everything above this
+/// type is shared between the two, so both kinds get the same validation and accounting.
+///
+/// Token streams work with both the Token and the Token-2022 program. Mints with a
+/// transfer fee are handled here: deposits are grossed up so the full amount reaches the
+/// vault, and the fee withheld on every payment out of it is reported to the caller.
+pub enum AssetVault<'info> {
+    Native {
+        stream: AccountInfo<'info>,
+        system_program: AccountInfo<'info>,
+    },
+    Token {
+        stream: AccountInfo<'info>,
+        vault: AccountInfo<'info>,
+        mint: AccountInfo<'info>,
+        token_program: AccountInfo<'info>,
+        decimals: u8,
+        stream_id: String,
+        sender: Pubkey,
+        bump: u8,
+    },
+}
+
+impl<'info> AssetVault<'info> {
+    /// Vault of a native stream.
+    pub fn native(
+        stream: &Account<'info, StreamAccount>,
+        system_program: &AccountInfo<'info>,
+    ) -> Result<Self> {
+        require!(stream.is_native(), MyError::IncorrectTokenAddress);
+
+        Ok(AssetVault::Native {
+            stream: stream.to_account_info(),
+            system_program: system_program.clone(),
+        })
+    }
+
+    /// Vault of a token stream, `vault` must be the associated token account of the stream
+    /// under the token program that owns `mint`.
+    pub fn token(
+        stream: &Account<'info, StreamAccount>,
+        vault: &AccountInfo<'info>,
+        mint: &AccountInfo<'info>,
+        token_program: &AccountInfo<'info>,
+    ) -> Result<Self> {
+        require!(!stream.is_native(), MyError::IncorrectTokenAddress);
+        require!(
+            mint.key() == stream.token_address && mint.owner == token_program.key,
+            MyError::IncorrectTokenAddress
+        );
+        require!(
+            vault.key()
+                == get_associated_token_address_with_program_id(
+                    &stream.key(),
+                    &stream.token_address,
+                    token_program.key
+                ),
+            MyError::AssociatedTokenAccountIncorrect
+        );
+
+        let decimals = {
+            let data = mint.try_borrow_data()?;
+            StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?
+                .base
+                .decimals
+        };
+
+        Ok(AssetVault::Token {
+            stream: stream.to_account_info(),
+            vault: vault.clone(),
+            mint: mint.clone(),
+            token_program: token_program.clone(),
+            decimals,
+            stream_id: stream.stream_id.clone(),
+            sender: stream.sender,
+            bump: stream.bump,
+        })
+    }
+
+    /// Account that has to be credited for a payment to `owner`.
+    pub fn destination_of(&self, owner: &Pubkey) -> Pubkey {
+        match self {
+            AssetVault::Native { .. } => *owner,
+            AssetVault::Token {
+                mint,
+                token_program,
+                ..
+            } => get_associated_token_address_with_program_id(owner, mint.key, token_program.key),
+        }
+    }
+
+    /// Moves `amount` into the vault, signed by `authority`, and returns what arrived.
+    ///
+    /// The transfer fee of the mint is paid by `authority` on top of `amount`.
+    pub fn deposit(
+        &self,
+        from: &AccountInfo<'info>,
+        authority: &AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<u64> {
+        match self {
+            AssetVault::Native {
+                stream,
+                system_program,
+            } => {
+                system_program::transfer(
+                    CpiContext::new(
+                        system_program.clone(),
+                        system_program::Transfer {
+                            from: authority.clone(),
+                            to: stream.clone(),
+                        },
+                    ),
+                    amount,
+                )?;
+                Ok(amount)
+            }
+            AssetVault::Token {
+                vault,
+                mint,
+                token_program,
+                decimals,
+                ..
+            } => {
+                let gross = amount
+                    .checked_add(inverse_transfer_fee(mint, amount)?)
+                    .ok_or(MyError::MathOverflow)?;
+                let before = token_balance(vault)?;
+
+                token_interface::transfer_checked(
+                    CpiContext::new(
+                        token_program.clone(),
+                        TransferChecked {
+                            from: from.clone(),
+                            mint: mint.clone(),
+                            to: vault.clone(),
+                            authority: authority.clone(),
+                        },
+                    ),
+                    gross,
+                    *decimals,
+                )?;
+
+                // Record what the vault received, not what was sent
+                token_balance(vault)?
+                    .checked_sub(before)
+                    .ok_or_else(|| error!(MyError::BalanceUnderflow))
+            }
+        }
+    }
+
+    /// Whether a payment of `amount` to `to` can go through.
+    ///
+    /// Lamports sent to a wallet that does not exist yet have to cover its rent, token
+    /// accounts take any amount.
+    pub fn can_receive(&self, to: &AccountInfo<'info>, amount: u64) -> Result<bool> {
+        match self {
+            AssetVault::Native { .. } => {
+                let balance = to
+                    .lamports()
+                    .checked_add(amount)
+                    .ok_or(MyError::MathOverflow)?;
+                Ok(Rent::get()?.is_exempt(balance, to.data_len()))
+            }
+            AssetVault::Token { .. } => Ok(true),
+        }
+    }
+
+    /// Moves `amount` out of the vault to `to`, signed by the stream PDA.
+    ///
+    /// Returns the part of `amount` withheld by the mint's transfer fee, zero otherwise.
+    pub fn pay(&self, to: &AccountInfo<'info>, amount: u64) -> Result<u64> {
+        if amount == 0 {
+            return Ok(0);
+        }
+
+        match self {
+            AssetVault::Native { stream, .. } => {
+                transfer_lamports(stream, to, amount)?;
+                Ok(0)
+            }
+            AssetVault::Token {
+                stream,
+                vault,
+                mint,
+                token_program,
+                decimals,
+                stream_id,
+                sender,
+                bump,
+            } => {
+                let seeds = &[stream_id.as_bytes(), sender.as_ref(), &[*bump]];
+                let withheld = transfer_fee(mint, amount)?;
+
+                token_interface::transfer_checked(
+                    CpiContext::new_with_signer(
+                        token_program.clone(),
+                        TransferChecked {
+                            from: vault.clone(),
+                            mint: mint.clone(),
+                            to: to.clone(),
+                            authority: stream.clone(),
+                        },
+                        &[&seeds[..]],
+                    ),
+                    amount,
+                    *decimals,
+                )?;
+
+                Ok(withheld)
+            }
+        }
+    }
+}
+
+/// Moves lamports out of a program-owned account with checked balances on both sides.
+fn transfer_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let from_balance = from
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(MyError::BalanceUnderflow)?;
+    let to_balance = to
+        .lamports()
+        .checked_add(amount)
+        .ok_or(MyError::MathOverflow)?;
+    **from.try_borrow_mut_lamports()? = from_balance;
+    **to.try_borrow_mut_lamports()? = to_balance;
+    Ok(())
+}
+
+fn token_balance(account: &AccountInfo) -> Result<u64> {
+    let data = account.try_borrow_data()?;
+    Ok(
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?
+            .base
+            .amount,
+    )
+}
+
+/// Fee withheld by the token program when `amount` is sent, zero without the extension.
+fn transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+
+    match mint.get_extension::<TransferFeeConfig>() {
+        Ok(config) => Ok(config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(MyError::MathOverflow)?),
+        Err(_) => Ok(0),
+    }
+}
+
+/// Fee to send on top of `amount` so that `amount` is received.
+fn inverse_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+
+    match mint.get_extension::<TransferFeeConfig>() {
+        Ok(config) => Ok(config
+            .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(MyError::MathOverflow)?),
+        Err(_) => Ok(0),
+    }
+}
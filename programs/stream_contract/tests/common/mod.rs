@@ -0,0 +1,518 @@
+//! Harness shared by the program-test suites: a bank with the program, its config and a
+//! clock the tests move by hand, plus helpers for keypairs, mints, balances and events.
+
+#![allow(dead_code)]
+
+use std::sync::{Mutex, Once};
+
+use anchor_lang::prelude::AccountInfo;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::program_stubs::{self, SyscallStubs};
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::clock::Clock;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config;
+use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use stream_client::{
+    accounts, errors, instructions, interval_duration, pda, Asset, CreateStreamParams, MyError,
+    SplitAccount, StreamAccount, StreamRef, StreamStatusView, ID,
+};
+
+pub use anchor_spl::token::spl_token;
+pub use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+// Unix time the clock is set to when a test starts
+pub const T0: u64 = 1_700_000_000;
+
+// Rent-exempt balance of a stream account without checkpoints
+pub fn stream_rent() -> u64 {
+    Rent::default().minimum_balance(16 + StreamAccount::MAX_SIZE)
+}
+
+// The entrypoint generated by Anchor wants the account infos to live as long as their data
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    stream_contract::entry(program_id, accounts, data)
+}
+
+// Data logged by the program in every bank of the process, which runs it natively
+static LOGGED_DATA: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+/// The syscalls of solana-program-test, which only print `sol_log_data`, with the data
+/// recorded so that the tests can read the events of the program.
+struct RecordLogData(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for RecordLogData {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        self.0.sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.0.sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.0
+            .sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_last_restart_slot(var_addr)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        LOGGED_DATA.lock().unwrap().push(fields.concat());
+        self.0.sol_log_data(fields)
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.0.sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.sol_get_stack_height()
+    }
+}
+
+// Holds the place of the bank's syscalls while they are wrapped
+struct Unset;
+
+impl SyscallStubs for Unset {}
+
+/// Events of type `T` emitted so far by any test, in order.
+pub fn events<T: Discriminator + AnchorDeserialize>() -> Vec<T> {
+    LOGGED_DATA
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|data| data.starts_with(&T::DISCRIMINATOR))
+        .map(|data| T::deserialize(&mut &data[8..]).unwrap())
+        .collect()
+}
+
+pub struct Env {
+    pub ctx: ProgramTestContext,
+    pub admin: Keypair,
+    pub treasury: Pubkey,
+    // Keeps otherwise identical transactions apart within one blockhash
+    nonce: u32,
+}
+
+impl Env {
+    /// The program with its config initialized, no protocol fee and the clock at `T0`.
+    pub async fn new() -> Self {
+        let mut env = Env::deployed().await;
+
+        let admin = env.admin.insecure_clone();
+        env.ok(instructions::initialize_config(&admin.pubkey()), &[&admin])
+            .await;
+        let treasury = env.treasury;
+        env.fund(&treasury, Rent::default().minimum_balance(0))
+            .await;
+        env.set_fee(0).await;
+        env
+    }
+
+    /// The program without a config, `admin` is its upgrade authority and holds 1 SOL.
+    pub async fn deployed() -> Self {
+        let mut program = ProgramTest::new("stream_contract", ID, processor!(process_instruction));
+        program.prefer_bpf(false);
+
+        // The native processor has no loader accounts, only the ProgramData is needed
+        let admin = Keypair::new();
+        let program_data = UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(admin.pubkey()),
+        };
+        program.add_account(
+            pda::program_data_address(),
+            Account::new_data(LAMPORTS_PER_SOL, &program_data, &bpf_loader_upgradeable::ID)
+                .unwrap(),
+        );
+
+        let ctx = program.start_with_context().await;
+
+        // The bank has installed its syscalls by now
+        static RECORD: Once = Once::new();
+        RECORD.call_once(|| {
+            let bank = program_stubs::set_syscall_stubs(Box::new(Unset));
+            program_stubs::set_syscall_stubs(Box::new(RecordLogData(bank)));
+        });
+
+        let mut env = Env {
+            ctx,
+            admin,
+            treasury: Keypair::new().pubkey(),
+            nonce: 0,
+        };
+        env.warp(T0).await;
+
+        let admin = env.admin.pubkey();
+        env.fund(&admin, LAMPORTS_PER_SOL).await;
+        env
+    }
+
+    /// Sets the protocol fee, keeping everything else of the config.
+    pub async fn set_fee(&mut self, fee_bps: u64) {
+        let admin = self.admin.insecure_clone();
+        let ix = instructions::update_config(
+            &admin.pubkey(),
+            false,
+            Vec::new(),
+            fee_bps,
+            &self.treasury,
+        );
+        self.ok(ix, &[&admin]).await;
+    }
+
+    pub async fn now(&mut self) -> u64 {
+        let clock: Clock = self.ctx.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp as u64
+    }
+
+    /// Moves to the next slot with the clock at `timestamp`.
+    pub async fn warp(&mut self, timestamp: u64) {
+        let mut clock: Clock = self.ctx.banks_client.get_sysvar().await.unwrap();
+        self.ctx.warp_to_slot(clock.slot + 1).unwrap();
+        clock.slot += 1;
+        clock.unix_timestamp = timestamp as i64;
+        self.ctx.set_sysvar(&clock);
+    }
+
+    pub async fn send(
+        &mut self,
+        ix: Instruction,
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        self.nonce += 1;
+        let unique = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000 - self.nonce);
+
+        let mut all = vec![&self.ctx.payer];
+        all.extend_from_slice(signers);
+        let blockhash = self.ctx.banks_client.get_latest_blockhash().await.unwrap();
+        let tx = Transaction::new_signed_with_payer(
+            &[unique, ix],
+            Some(&self.ctx.payer.pubkey()),
+            &all,
+            blockhash,
+        );
+        self.ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn ok(&mut self, ix: Instruction, signers: &[&Keypair]) {
+        if let Err(err) = self.send(ix, signers).await {
+            panic!("transaction failed: {err}");
+        }
+    }
+
+    /// Sends `ix` and checks that it fails with `expected`.
+    pub async fn fails(&mut self, ix: Instruction, signers: &[&Keypair], expected: MyError) {
+        let err = self
+            .send(ix, signers)
+            .await
+            .expect_err("transaction succeeded");
+        let actual = errors::from_transaction_error(&err.unwrap());
+        assert_eq!(
+            actual.map(|e| e.name()),
+            Some(expected.name()),
+            "unexpected error: {err}"
+        );
+    }
+
+    /// Sends `ix` and returns the error of the transaction, whatever it is.
+    pub async fn error(&mut self, ix: Instruction, signers: &[&Keypair]) -> TransactionError {
+        self.send(ix, signers)
+            .await
+            .expect_err("transaction succeeded")
+            .unwrap()
+    }
+
+    pub async fn fund(&mut self, to: &Pubkey, lamports: u64) {
+        let ix = system_instruction::transfer(&self.ctx.payer.pubkey(), to, lamports);
+        self.ok(ix, &[]).await;
+    }
+
+    /// A new keypair holding 10 SOL.
+    pub async fn user(&mut self) -> Keypair {
+        let user = Keypair::new();
+        self.fund(&user.pubkey(), 10 * LAMPORTS_PER_SOL).await;
+        user
+    }
+
+    pub async fn account(&mut self, address: &Pubkey) -> Option<Account> {
+        self.ctx.banks_client.get_account(*address).await.unwrap()
+    }
+
+    pub async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.account(address).await.map_or(0, |a| a.lamports)
+    }
+
+    pub async fn stream(&mut self, address: &Pubkey) -> StreamAccount {
+        let account = self.account(address).await.expect("no stream account");
+        accounts::decode_stream(&account.data).unwrap()
+    }
+
+    pub async fn split(&mut self, stream: &Pubkey) -> SplitAccount {
+        let account = self
+            .account(&pda::split_address(stream))
+            .await
+            .expect("no split account");
+        accounts::decode_split(&account.data).unwrap()
+    }
+
+    /// The view returned by get_stream_status, from a simulation at the current clock.
+    pub async fn status(&mut self, stream: &Pubkey) -> StreamStatusView {
+        let blockhash = self.ctx.banks_client.get_latest_blockhash().await.unwrap();
+        let tx = Transaction::new_signed_with_payer(
+            &[instructions::get_stream_status(stream)],
+            Some(&self.ctx.payer.pubkey()),
+            &[&self.ctx.payer],
+            blockhash,
+        );
+        let simulation = self
+            .ctx
+            .banks_client
+            .simulate_transaction(tx)
+            .await
+            .unwrap();
+        let return_data = simulation
+            .simulation_details
+            .unwrap()
+            .return_data
+            .expect("no return data");
+        accounts::decode_status(&return_data.data).unwrap()
+    }
+
+    /// Keys of an existing stream for the instruction builders.
+    pub async fn keys(&mut self, address: &Pubkey, asset: Asset) -> StreamRef {
+        let stream = self.stream(address).await;
+        StreamRef {
+            asset,
+            ..StreamRef::new(&stream, Pubkey::default())
+        }
+    }
+
+    /// Creates a stream and returns its keys.
+    pub async fn create(
+        &mut self,
+        sender: &Keypair,
+        recipient: &Pubkey,
+        asset: Asset,
+        params: &CreateStreamParams,
+    ) -> StreamRef {
+        let ix = instructions::create_stream(&sender.pubkey(), recipient, &asset, params);
+        self.ok(ix, &[sender]).await;
+        let address = pda::stream_address(&params.stream_id, &sender.pubkey());
+        self.keys(&address, asset).await
+    }
+
+    /// A mint of `token_program` with `decimals` and `mint_authority` as authority, with a
+    /// transfer fee of `fee_bps` capped at `max_fee` when set (Token-2022 only).
+    pub async fn mint(
+        &mut self,
+        token_program: Pubkey,
+        decimals: u8,
+        transfer_fee: Option<(u16, u64)>,
+    ) -> Asset {
+        let mint = Keypair::new();
+        let payer = self.ctx.payer.pubkey();
+        let extensions = match transfer_fee {
+            Some(_) => vec![ExtensionType::TransferFeeConfig],
+            None => vec![],
+        };
+        let space = match token_program == spl_token::ID {
+            true => spl_token::state::Mint::LEN,
+            false => {
+                ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&extensions)
+                    .unwrap()
+            }
+        };
+
+        let create = system_instruction::create_account(
+            &payer,
+            &mint.pubkey(),
+            Rent::default().minimum_balance(space),
+            space as u64,
+            &token_program,
+        );
+        self.ok(create, &[&mint]).await;
+
+        if let Some((fee_bps, max_fee)) = transfer_fee {
+            let ix = initialize_transfer_fee_config(
+                &token_program,
+                &mint.pubkey(),
+                None,
+                None,
+                fee_bps,
+                max_fee,
+            )
+            .unwrap();
+            self.ok(ix, &[]).await;
+        }
+
+        let ix = spl_token_2022::instruction::initialize_mint2(
+            &token_program,
+            &mint.pubkey(),
+            &payer,
+            None,
+            decimals,
+        )
+        .unwrap();
+        self.ok(ix, &[]).await;
+
+        Asset::Token {
+            mint: mint.pubkey(),
+            token_program,
+        }
+    }
+
+    /// The associated token account of `owner`, created if needed.
+    pub async fn token_account(&mut self, owner: &Pubkey, asset: &Asset) -> Pubkey {
+        let Asset::Token {
+            mint,
+            token_program,
+        } = asset
+        else {
+            panic!("not a token asset");
+        };
+        let address = pda::token_account_address(owner, mint, token_program);
+
+        if self.account(&address).await.is_none() {
+            let ix = spl_associated_token_account::instruction::create_associated_token_account(
+                &self.ctx.payer.pubkey(),
+                owner,
+                mint,
+                token_program,
+            );
+            self.ok(ix, &[]).await;
+        }
+        address
+    }
+
+    /// Mints `amount` to the associated token account of `owner`.
+    pub async fn mint_to(&mut self, owner: &Pubkey, asset: &Asset, amount: u64) {
+        let account = self.token_account(owner, asset).await;
+        let Asset::Token {
+            mint,
+            token_program,
+        } = asset
+        else {
+            unreachable!()
+        };
+        let ix = spl_token_2022::instruction::mint_to(
+            token_program,
+            mint,
+            &account,
+            &self.ctx.payer.pubkey(),
+            &[],
+            amount,
+        )
+        .unwrap();
+        self.ok(ix, &[]).await;
+    }
+
+    /// Token balance of the associated token account of `owner`, zero when it is missing.
+    pub async fn tokens(&mut self, owner: &Pubkey, asset: &Asset) -> u64 {
+        let Asset::Token {
+            mint,
+            token_program,
+        } = asset
+        else {
+            panic!("not a token asset");
+        };
+        let address = pda::token_account_address(owner, mint, token_program);
+        match self.account(&address).await {
+            Some(account) => {
+                StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+                    .unwrap()
+                    .base
+                    .amount
+            }
+            None => 0,
+        }
+    }
+
+    /// Fee withheld in the token account of `owner` by a transfer fee mint.
+    pub async fn withheld(&mut self, owner: &Pubkey, asset: &Asset) -> u64 {
+        use spl_token_2022::extension::transfer_fee::TransferFeeAmount;
+
+        let Asset::Token {
+            mint,
+            token_program,
+        } = asset
+        else {
+            panic!("not a token asset");
+        };
+        let address = pda::token_account_address(owner, mint, token_program);
+        let account = self.account(&address).await.expect("no token account");
+        let state =
+            StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data).unwrap();
+        state
+            .get_extension::<TransferFeeAmount>()
+            .map_or(0, |fee| u64::from(fee.withheld_amount))
+    }
+}
+
+/// Parameters of a stream of `amount` paid `rate` every `interval` seconds from `start`.
+pub fn params(id: &str, amount: u64, interval: u64, rate: u64, start: u64) -> CreateStreamParams {
+    CreateStreamParams {
+        stream_id: id.to_string(),
+        stream_title: "payroll".to_string(),
+        amount,
+        cliff_amount: 0,
+        is_cliff_percent: false,
+        start,
+        cliff_time: 0,
+        interval,
+        rate,
+        duration: interval_duration(amount, interval, rate).unwrap(),
+        is_infinite: false,
+        // Sender cancels, pauses, resumes and edits, the recipient withdraws
+        cancel_by: 0,
+        pause_by: 0,
+        resume_by: 0,
+        withdraw_by: 1,
+        edit_by: 0,
+        start_now: false,
+        partner: None,
+        partner_fee_bps: 0,
+        is_linear: false,
+    }
+}
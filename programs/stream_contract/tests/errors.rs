@@ -0,0 +1,721 @@
+//! Every `MyError` the program can raise, each from the instruction and state that
+//! triggers it.
+//!
+//! The exception is `BalanceUnderflow`, which guards the accounting of a stream and which
+//! no known sequence of instructions reaches. The last one that did, reloading an infinite
+//! split stream its recipients had drained, is covered in `token.rs`.
+
+mod common;
+
+use common::*;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use stream_client::{
+    instructions, pda, Asset, Checkpoint, CreateStreamParams, MyError, SplitRecipient, StreamRef,
+};
+
+// Turns valid parameters into invalid ones
+type Change = fn(&mut CreateStreamParams);
+
+/// A running native stream from `sender` to `recipient`, 1_000 a second until T0 + 1_000.
+async fn running(env: &mut Env, sender: &Keypair, recipient: &Keypair, id: &str) -> StreamRef {
+    let mut params = params(id, 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    env.create(sender, &recipient.pubkey(), Asset::Native, &params)
+        .await
+}
+
+/// Replaces the account `from` of `ix` by `to`.
+fn swap_account(mut ix: Instruction, from: &Pubkey, to: &Pubkey) -> Instruction {
+    for meta in ix.accounts.iter_mut().filter(|m| m.pubkey == *from) {
+        meta.pubkey = *to;
+    }
+    ix
+}
+
+#[tokio::test]
+async fn create_rejects_invalid_params() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let base = params("invalid", 1_000_000, 10, 100_000, T0 + 100);
+
+    let cases: Vec<(Change, MyError)> = vec![
+        (|p| p.stream_title = "x".repeat(51), MyError::TitleTooLong),
+        (|p| p.amount = 0, MyError::DepositIsZero),
+        (|p| p.start = T0 - 1, MyError::PastStartTime),
+        (|p| p.cancel_by = 4, MyError::InvalidCancelBy),
+        (|p| p.pause_by = 4, MyError::InvalidPauseBy),
+        (|p| p.withdraw_by = 3, MyError::InvalidWithdrawBy),
+        (|p| p.resume_by = 4, MyError::InvalidResumeBy),
+        (|p| p.edit_by = 3, MyError::InvalidEditBy),
+        (|p| p.partner_fee_bps = 10, MyError::IncorrectPartner),
+        (
+            |p| {
+                p.partner = Some(Pubkey::new_unique());
+                p.partner_fee_bps = 1_001;
+            },
+            MyError::FeeTooHigh,
+        ),
+        (|p| p.interval = 0, MyError::IntervalIsZero),
+        (
+            |p| {
+                p.is_cliff_percent = true;
+                p.cliff_amount = 10_001;
+            },
+            MyError::InvalidCliffPercent,
+        ),
+        (|p| p.cliff_amount = 1_000_001, MyError::CliffExceedsDeposit),
+        (|p| p.rate = 1_000_001, MyError::DepositSmallerThanTime),
+        (|p| p.duration = 0, MyError::FutureStartTime),
+        (|p| p.duration = 101, MyError::IncorrectDuration),
+        (
+            // Rounds to the rate, but would stop long after everything has vested
+            |p| {
+                p.amount = 1_000;
+                p.interval = 1;
+                p.rate = 1;
+                p.duration = 1_999;
+            },
+            MyError::IncorrectDuration,
+        ),
+        (|p| p.start = u64::MAX - 10, MyError::MathOverflow),
+        (|p| p.cliff_time = T0 + 201, MyError::InvalidCliffTime),
+        (|p| p.cliff_time = T0 + 99, MyError::InvalidCliffTime),
+    ];
+
+    for (change, expected) in cases {
+        let mut params = base.clone();
+        change(&mut params);
+        let ix = instructions::create_stream(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            &Asset::Native,
+            &params,
+        );
+        env.fails(ix, &[&sender], expected).await;
+    }
+
+    let ix = instructions::create_stream(&sender.pubkey(), &sender.pubkey(), &Asset::Native, &base);
+    env.fails(ix, &[&sender], MyError::SenderIsRecipient).await;
+
+    // The stream PDA is derived from the id, a mismatched batch entry is caught
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_000_000).await;
+    let Asset::Token {
+        mint,
+        token_program,
+    } = asset
+    else {
+        unreachable!()
+    };
+    let ix = instructions::create_streams_batch(
+        &sender.pubkey(),
+        &mint,
+        &token_program,
+        &[(recipient.pubkey(), base.clone())],
+    );
+    let ix = swap_account(
+        ix,
+        &pda::stream_address(&base.stream_id, &sender.pubkey()),
+        &pda::stream_address("other", &sender.pubkey()),
+    );
+    env.fails(ix, &[&sender], MyError::IncorrectStreamId).await;
+
+    let ix = instructions::create_streams_batch(&sender.pubkey(), &mint, &token_program, &[]);
+    env.fails(ix, &[&sender], MyError::InvalidBatch).await;
+}
+
+#[tokio::test]
+async fn withdraw_errors() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let stream = running(&mut env, &sender, &recipient, "withdraw").await;
+    let withdraw =
+        |amount| instructions::withdraw(&stream, &recipient.pubkey(), Some(&treasury), amount);
+
+    env.fails(withdraw(None), &[&recipient], MyError::NothingToWithdraw)
+        .await;
+
+    env.warp(T0 + 10).await;
+    env.fails(withdraw(Some(0)), &[&recipient], MyError::AmountIsZero)
+        .await;
+    env.fails(
+        withdraw(Some(10_001)),
+        &[&recipient],
+        MyError::AmountExceedsWithdrawable,
+    )
+    .await;
+    env.fails(
+        instructions::withdraw(&stream, &sender.pubkey(), Some(&treasury), None),
+        &[&sender],
+        MyError::NotAuthorized,
+    )
+    .await;
+
+    let wrong_id = StreamRef {
+        stream_id: "other".to_string(),
+        ..stream.clone()
+    };
+    env.fails(
+        instructions::withdraw(&wrong_id, &recipient.pubkey(), Some(&treasury), None),
+        &[&recipient],
+        MyError::IncorrectStreamId,
+    )
+    .await;
+
+    let stranger = env.user().await;
+    let wrong_recipient = StreamRef {
+        recipient: stranger.pubkey(),
+        ..stream.clone()
+    };
+    env.fails(
+        instructions::withdraw(&wrong_recipient, &stranger.pubkey(), Some(&treasury), None),
+        &[&stranger],
+        MyError::IncorrectRecipient,
+    )
+    .await;
+
+    env.fails(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&stranger.pubkey()), None),
+        &[&recipient],
+        MyError::InvalidTreasury,
+    )
+    .await;
+
+    // Config changes apply to streams that already exist
+    let admin = env.admin.insecure_clone();
+    env.ok(
+        instructions::update_config(&admin.pubkey(), true, vec![], 0, &treasury),
+        &[&admin],
+    )
+    .await;
+    env.fails(withdraw(None), &[&recipient], MyError::ProgramHalted)
+        .await;
+}
+
+#[tokio::test]
+async fn lifecycle_errors() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+
+    let params = params("later", 1_000_000, 1, 1_000, T0 + 100);
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    let pause = instructions::pause(&stream, &sender.pubkey(), Some(&treasury));
+    let resume = instructions::resume(&stream, &sender.pubkey());
+
+    env.fails(pause.clone(), &[&sender], MyError::StreamNotStarted)
+        .await;
+    env.fails(resume.clone(), &[&sender], MyError::StreamNotStarted)
+        .await;
+
+    env.warp(T0 + 150).await;
+    env.fails(resume.clone(), &[&sender], MyError::StreamNotPaused)
+        .await;
+    env.fails(
+        instructions::pause(&stream, &recipient.pubkey(), Some(&treasury)),
+        &[&recipient],
+        MyError::NotAuthorized,
+    )
+    .await;
+    env.fails(
+        instructions::reload(&stream, 1_000),
+        &[&sender],
+        MyError::NotInfiniteStream,
+    )
+    .await;
+    // Only an emptied stream can be deleted, whatever its status
+    env.fails(
+        instructions::delete(&stream),
+        &[&sender],
+        MyError::StreamNotEmpty,
+    )
+    .await;
+
+    env.ok(pause.clone(), &[&sender]).await;
+    env.fails(pause.clone(), &[&sender], MyError::StreamAlreadyPaused)
+        .await;
+    env.fails(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&treasury), None),
+        &[&recipient],
+        MyError::StreamIsPaused,
+    )
+    .await;
+    env.ok(resume, &[&sender]).await;
+
+    // Resumed right away, the stream still stops at T0 + 1_100
+    env.warp(T0 + 1_200).await;
+    env.fails(pause, &[&sender], MyError::StreamAlreadyEnded)
+        .await;
+    // The status table has no reload for a completed stream
+    env.fails(
+        instructions::reload(&stream, 1_000),
+        &[&sender],
+        MyError::InvalidStateTransition,
+    )
+    .await;
+
+    env.ok(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+    )
+    .await;
+    env.fails(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+        MyError::StreamAlreadyCancelled,
+    )
+    .await;
+    env.fails(
+        instructions::resume(&stream, &sender.pubkey()),
+        &[&sender],
+        MyError::StreamAlreadyCancelled,
+    )
+    .await;
+    let ix = instructions::edit(
+        &stream,
+        &sender.pubkey(),
+        Some(&treasury),
+        String::new(),
+        1,
+        1_000,
+        1_000,
+    );
+    env.fails(ix, &[&sender], MyError::StreamAlreadyCancelled)
+        .await;
+
+    // Only the sender gets the rent back
+    let wrong_sender = StreamRef {
+        sender: recipient.pubkey(),
+        ..stream.clone()
+    };
+    env.fails(
+        instructions::delete(&wrong_sender),
+        &[&recipient],
+        MyError::NotAuthorized,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn edit_and_reload_errors() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let stream = running(&mut env, &sender, &recipient, "edited").await;
+    let edit = |title: &str, interval, rate, duration| {
+        instructions::edit(
+            &stream,
+            &sender.pubkey(),
+            Some(&treasury),
+            title.to_string(),
+            interval,
+            rate,
+            duration,
+        )
+    };
+
+    let cases = [
+        (
+            edit(&"x".repeat(51), 1, 1_000, 1_000),
+            MyError::TitleTooLong,
+        ),
+        (edit("", 0, 1_000, 1_000), MyError::IntervalIsZero),
+        (edit("", 1, 2_000_000, 1), MyError::DepositSmallerThanTime),
+        (edit("", 1, 1_000, 0), MyError::FutureStartTime),
+        (edit("", 1, 1_000, 999), MyError::IncorrectDuration),
+    ];
+    for (ix, expected) in cases {
+        env.fails(ix, &[&sender], expected).await;
+    }
+    env.fails(
+        instructions::edit(
+            &stream,
+            &recipient.pubkey(),
+            Some(&treasury),
+            String::new(),
+            1,
+            1_000,
+            1_000,
+        ),
+        &[&recipient],
+        MyError::NotAuthorized,
+    )
+    .await;
+
+    // Linear streams spread their balance over the time left, they have no rate to edit
+    let mut params = params("linear", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    params.is_linear = true;
+    params.is_infinite = true;
+    let linear = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    let ix = instructions::edit(
+        &linear,
+        &sender.pubkey(),
+        Some(&treasury),
+        String::new(),
+        1,
+        1_000,
+        1_000,
+    );
+    env.fails(ix, &[&sender], MyError::ScheduleNotEditable)
+        .await;
+
+    env.fails(
+        instructions::reload(&linear, 0),
+        &[&sender],
+        MyError::DepositIsZero,
+    )
+    .await;
+    env.fails(
+        instructions::reload(&linear, 999),
+        &[&sender],
+        MyError::DepositSmallerThanTime,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn cliff_errors() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+
+    let mut params = params("cliff", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    params.cliff_time = T0 + 100;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+
+    env.warp(T0 + 50).await;
+    env.fails(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None),
+        &[&recipient],
+        MyError::CliffNotReached,
+    )
+    .await;
+
+    // A paused stream cannot be shortened past the time left to its cliff
+    env.ok(
+        instructions::pause(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+    )
+    .await;
+    let ix = instructions::edit(
+        &stream,
+        &sender.pubkey(),
+        Some(&env.treasury),
+        String::new(),
+        1,
+        95_000,
+        10,
+    );
+    env.fails(ix, &[&sender], MyError::InvalidCliffTime).await;
+}
+
+#[tokio::test]
+async fn token_account_errors() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    let Asset::Token {
+        mint,
+        token_program,
+    } = asset
+    else {
+        unreachable!()
+    };
+    env.mint_to(&sender.pubkey(), &asset, 1_000_000).await;
+
+    let mut params = params("tokens", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+    env.warp(T0 + 10).await;
+
+    // A native instruction on a token stream
+    let as_native = StreamRef {
+        asset: Asset::Native,
+        ..stream.clone()
+    };
+    env.fails(
+        instructions::withdraw(&as_native, &recipient.pubkey(), Some(&treasury), None),
+        &[&recipient],
+        MyError::IncorrectTokenAddress,
+    )
+    .await;
+
+    let wrong_sender = StreamRef {
+        sender: recipient.pubkey(),
+        ..stream.clone()
+    };
+    env.fails(
+        instructions::withdraw(&wrong_sender, &recipient.pubkey(), Some(&treasury), None),
+        &[&recipient],
+        MyError::IncorrectSender,
+    )
+    .await;
+
+    // A token account of the stream that is not its associated token account
+    let other = Keypair::new();
+    let space = spl_token::state::Account::LEN;
+    let create = system_instruction::create_account(
+        &env.ctx.payer.pubkey(),
+        &other.pubkey(),
+        Rent::default().minimum_balance(space),
+        space as u64,
+        &token_program,
+    );
+    env.ok(create, &[&other]).await;
+    let init = spl_token::instruction::initialize_account3(
+        &token_program,
+        &other.pubkey(),
+        &mint,
+        &stream.address,
+    )
+    .unwrap();
+    env.ok(init, &[]).await;
+    let vault = pda::token_account_address(&stream.address, &mint, &token_program);
+    let ix = swap_account(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&treasury), None),
+        &vault,
+        &other.pubkey(),
+    );
+    env.fails(ix, &[&recipient], MyError::AssociatedTokenAccountIncorrect)
+        .await;
+
+    // Paying out of the vault into the vault
+    env.fails(
+        instructions::withdraw_to(&stream, &vault, Some(&treasury), None),
+        &[&recipient],
+        MyError::InvalidDestination,
+    )
+    .await;
+
+    let ix =
+        instructions::withdraw_batch(&recipient.pubkey(), &token_program, Some(&treasury), &[]);
+    env.fails(ix, &[&recipient], MyError::InvalidBatch).await;
+}
+
+#[tokio::test]
+async fn only_the_upgrade_authority_initializes_the_config() {
+    let mut env = Env::deployed().await;
+    let admin = env.admin.insecure_clone();
+    let stranger = env.user().await;
+
+    env.fails(
+        instructions::initialize_config(&stranger.pubkey()),
+        &[&stranger],
+        MyError::NotAuthorized,
+    )
+    .await;
+    env.ok(instructions::initialize_config(&admin.pubkey()), &[&admin])
+        .await;
+}
+
+#[tokio::test]
+async fn config_errors() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let admin = env.admin.insecure_clone();
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let update = |allowed_mints: Vec<Pubkey>, fee_bps, treasury: &Pubkey| {
+        instructions::update_config(&admin.pubkey(), false, allowed_mints, fee_bps, treasury)
+    };
+
+    env.fails(
+        update(vec![Pubkey::new_unique(); 21], 0, &treasury),
+        &[&admin],
+        MyError::TooManyMints,
+    )
+    .await;
+    env.fails(
+        update(vec![], 1_001, &treasury),
+        &[&admin],
+        MyError::FeeTooHigh,
+    )
+    .await;
+    env.fails(
+        update(vec![], 0, &Pubkey::default()),
+        &[&admin],
+        MyError::InvalidTreasury,
+    )
+    .await;
+    env.fails(
+        instructions::update_config(&sender.pubkey(), false, vec![], 0, &treasury),
+        &[&sender],
+        MyError::NotAuthorized,
+    )
+    .await;
+    env.fails(
+        instructions::accept_admin(&sender.pubkey()),
+        &[&sender],
+        MyError::NotAuthorized,
+    )
+    .await;
+
+    // Only allowed mints can be streamed once the list is set
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_000).await;
+    env.ok(update(vec![Pubkey::new_unique()], 0, &treasury), &[&admin])
+        .await;
+    let params = params("not-allowed", 1_000, 1, 10, T0 + 10);
+    let ix = instructions::create_stream(&sender.pubkey(), &recipient.pubkey(), &asset, &params);
+    env.fails(ix, &[&sender], MyError::MintNotAllowed).await;
+
+    env.ok(
+        instructions::update_config(&admin.pubkey(), true, vec![], 0, &treasury),
+        &[&admin],
+    )
+    .await;
+    let ix = instructions::create_stream(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &Asset::Native,
+        &params,
+    );
+    env.fails(ix, &[&sender], MyError::ProgramHalted).await;
+}
+
+#[tokio::test]
+async fn split_and_step_errors() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let first = env.user().await;
+    let second = env.user().await;
+    let share = |recipient: &Keypair, weight_bps| SplitRecipient {
+        recipient: recipient.pubkey(),
+        weight_bps,
+    };
+
+    let mut params = params("split", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    let create_split = |recipients| {
+        instructions::create_split_stream(&sender.pubkey(), &Asset::Native, &params, recipients)
+    };
+
+    let cases = [
+        (vec![share(&first, 10_000)], MyError::InvalidSplit),
+        (
+            vec![share(&first, 5_000), share(&second, 4_000)],
+            MyError::InvalidSplit,
+        ),
+        (
+            vec![share(&first, 10_000), share(&second, 0)],
+            MyError::InvalidSplit,
+        ),
+        (
+            vec![share(&first, 5_000), share(&first, 5_000)],
+            MyError::InvalidSplit,
+        ),
+        (
+            vec![share(&first, 5_000), share(&sender, 5_000)],
+            MyError::SenderIsRecipient,
+        ),
+    ];
+    for (recipients, expected) in cases {
+        env.fails(create_split(recipients), &[&sender], expected)
+            .await;
+    }
+
+    env.ok(
+        create_split(vec![share(&first, 5_000), share(&second, 5_000)]),
+        &[&sender],
+    )
+    .await;
+    let address = pda::stream_address(&params.stream_id, &sender.pubkey());
+    let stream = env.keys(&address, Asset::Native).await;
+
+    env.warp(T0 + 10).await;
+    env.fails(
+        instructions::withdraw(&stream, &first.pubkey(), Some(&treasury), None),
+        &[&first],
+        MyError::SplitStream,
+    )
+    .await;
+    env.fails(
+        instructions::withdraw_share(&stream, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+        MyError::NotInSplit,
+    )
+    .await;
+
+    // Checkpoints must rise in time and amount and unlock exactly the deposit
+    let step = |at: u64, unlocked| Checkpoint {
+        timestamp: T0 + at,
+        cumulative_unlocked: unlocked,
+    };
+    let mut params = params.clone();
+    params.stream_id = "steps".to_string();
+    let cases = [
+        vec![],
+        vec![step(100, 500_000)],
+        vec![step(100, 500_000), step(100, 1_000_000)],
+        vec![step(100, 500_000), step(200, 500_000)],
+        vec![step(100, 1_500_000)],
+    ];
+    for checkpoints in cases {
+        let ix = instructions::create_step_stream(
+            &sender.pubkey(),
+            &first.pubkey(),
+            &Asset::Native,
+            &params,
+            checkpoints,
+        );
+        env.fails(ix, &[&sender], MyError::InvalidCheckpoints).await;
+    }
+
+    params.is_infinite = true;
+    let ix = instructions::create_step_stream(
+        &sender.pubkey(),
+        &first.pubkey(),
+        &Asset::Native,
+        &params,
+        vec![step(100, 1_000_000)],
+    );
+    env.fails(ix, &[&sender], MyError::InvalidCheckpoints).await;
+}
+
+#[tokio::test]
+async fn reload_past_the_largest_timestamp_overflows() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+
+    // 1 every 2^62 seconds, the stop time is already past 2^63
+    let mut params = params("forever", 2, 1 << 62, 1, 0);
+    params.start_now = true;
+    params.is_infinite = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    assert_eq!(env.stream(&stream.address).await.stop_time, T0 + (1 << 63));
+
+    // Two more would stop after u64::MAX, nothing wraps around
+    env.fails(
+        instructions::reload(&stream, 2),
+        &[&sender],
+        MyError::MathOverflow,
+    )
+    .await;
+    assert_eq!(env.stream(&stream.address).await.deposit, 2);
+}
@@ -0,0 +1,337 @@
+//! Random lifecycles of a stream run against the program, checking after every step that
+//! no funds are created or lost.
+//!
+//! Each case creates one native or token stream and applies a random sequence of clock
+//! jumps, withdrawals, pauses, resumes, reloads and a cancel, then drains the stream. After
+//! every step the vault must hold exactly `remaining_balance`, and everything deposited
+//! must be accounted for as withdrawn, refunded to the sender or still in the vault. When
+//! nothing is left, `withdrawn + refunded == deposit`.
+//!
+//! Failing cases are shrunk to a minimal sequence. `PROPTEST_CASES` sets the number of
+//! cases. They share one bank per test thread, as banks are never freed and their threads
+//! would pile up; every case uses fresh keypairs and starts from the current clock.
+
+mod common;
+
+use common::*;
+use proptest::prelude::*;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::cell::RefCell;
+use stream_client::{
+    instructions, interval_duration, Asset, CreateStreamParams, StreamAccount, StreamRef,
+};
+
+#[derive(Clone, Debug)]
+enum Op {
+    Warp(u64),
+    // None withdraws everything withdrawable
+    Withdraw(Option<u64>),
+    Pause,
+    Resume,
+    Reload(u64),
+    Cancel,
+}
+
+#[derive(Clone, Debug)]
+struct Scenario {
+    token: bool,
+    interval: u64,
+    rate: u64,
+    intervals: u64,
+    // Streamed in one last, shorter interval
+    leftover: u64,
+    cliff_amount: u64,
+    // Seconds from the start, 0 for no cliff date
+    cliff_after: u64,
+    start_after: u64,
+    is_infinite: bool,
+    is_linear: bool,
+    fee_bps: u64,
+    partner_fee_bps: u64,
+    ops: Vec<Op>,
+}
+
+impl Scenario {
+    fn params(&self, partner: Pubkey, now: u64) -> CreateStreamParams {
+        let streamed = self.rate * self.intervals + self.leftover;
+        let mut params = params(
+            "invariants",
+            streamed + self.cliff_amount,
+            self.interval,
+            self.rate,
+            now + self.start_after,
+        );
+        // The cliff amount is not streamed
+        params.duration = interval_duration(streamed, self.interval, self.rate).unwrap();
+        params.cliff_amount = self.cliff_amount;
+        if self.cliff_after > 0 {
+            params.cliff_time = now + self.start_after + self.cliff_after;
+        }
+        params.is_infinite = self.is_infinite;
+        params.is_linear = self.is_linear;
+        if self.partner_fee_bps > 0 {
+            params.partner = Some(partner);
+            params.partner_fee_bps = self.partner_fee_bps;
+        }
+        params
+    }
+
+    fn reloads(&self) -> u64 {
+        self.ops
+            .iter()
+            .map(|op| match op {
+                Op::Reload(amount) => *amount,
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+fn op(rate: u64) -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => (1u64..400).prop_map(Op::Warp),
+        3 => proptest::option::of(1..=rate * 3).prop_map(Op::Withdraw),
+        1 => Just(Op::Pause),
+        1 => Just(Op::Resume),
+        1 => (1..=rate * 5).prop_map(Op::Reload),
+        1 => Just(Op::Cancel),
+    ]
+}
+
+fn scenario() -> impl Strategy<Value = Scenario> {
+    (1u64..=20, 1u64..=5_000, 1u64..=40).prop_flat_map(|(interval, rate, intervals)| {
+        let leftover = 0..rate;
+        let flags = (any::<bool>(), any::<bool>(), any::<bool>());
+        let fees = (
+            prop_oneof![Just(0u64), 1u64..=1_000],
+            prop_oneof![Just(0u64), 1u64..=1_000],
+        );
+        (
+            leftover,
+            0..=rate * intervals,
+            0..=interval * intervals,
+            0u64..100,
+            flags,
+            fees,
+            proptest::collection::vec(op(rate), 1..25),
+        )
+            .prop_map(
+                move |(
+                    leftover,
+                    cliff_amount,
+                    cliff_after,
+                    start_after,
+                    (token, is_infinite, is_linear),
+                    (fee_bps, partner_fee_bps),
+                    ops,
+                )| Scenario {
+                    token,
+                    interval,
+                    rate,
+                    intervals,
+                    leftover,
+                    cliff_amount,
+                    cliff_after,
+                    start_after,
+                    is_infinite,
+                    is_linear,
+                    fee_bps,
+                    partner_fee_bps,
+                    ops,
+                },
+            )
+    })
+}
+
+struct Parties {
+    sender: Keypair,
+    recipient: Keypair,
+    partner: Keypair,
+    asset: Asset,
+}
+
+/// Balances of everyone the stream moves funds between, in lamports or tokens.
+#[derive(Clone, Copy, Debug)]
+struct Balances {
+    sender: u64,
+    // Recipient, treasury and partner together
+    payees: u64,
+    vault: u64,
+}
+
+async fn balance(env: &mut Env, owner: &Pubkey, asset: &Asset) -> u64 {
+    match asset {
+        Asset::Native => env.lamports(owner).await,
+        Asset::Token { .. } => env.tokens(owner, asset).await,
+    }
+}
+
+async fn payees(env: &mut Env, parties: &Parties) -> u64 {
+    let treasury = env.treasury;
+    let asset = parties.asset;
+    balance(env, &parties.recipient.pubkey(), &asset).await
+        + balance(env, &treasury, &asset).await
+        + balance(env, &parties.partner.pubkey(), &asset).await
+}
+
+async fn balances(env: &mut Env, parties: &Parties, stream: &StreamRef) -> Balances {
+    let asset = parties.asset;
+    let vault = match asset {
+        Asset::Native => env.lamports(&stream.address).await - stream_rent(),
+        Asset::Token { .. } => env.tokens(&stream.address, &asset).await,
+    };
+    Balances {
+        sender: balance(env, &parties.sender.pubkey(), &asset).await,
+        payees: payees(env, parties).await,
+        vault,
+    }
+}
+
+/// What the test has seen move in and out of the stream.
+#[derive(Debug)]
+struct Ledger {
+    deposited: u64,
+    refunded: u64,
+    // Payees' balances before the stream was created
+    payees_before: u64,
+}
+
+impl Ledger {
+    fn check(&self, stream: &StreamAccount, balances: Balances) {
+        let paid = balances.payees - self.payees_before;
+        assert_eq!(
+            balances.vault, stream.remaining_balance,
+            "vault != remaining"
+        );
+        assert_eq!(stream.deposit, self.deposited, "deposit != deposited");
+        assert_eq!(stream.withdrawn, paid, "withdrawn != paid out");
+        assert_eq!(
+            paid + self.refunded + balances.vault,
+            self.deposited,
+            "funds created or lost"
+        );
+    }
+}
+
+async fn run(env: &mut Env, scenario: Scenario) {
+    env.set_fee(scenario.fee_bps).await;
+    let treasury = env.treasury;
+
+    let parties = Parties {
+        sender: env.user().await,
+        recipient: env.user().await,
+        partner: env.user().await,
+        asset: Asset::Native,
+    };
+    let now = env.now().await;
+    let params = scenario.params(parties.partner.pubkey(), now);
+    let asset = match scenario.token {
+        true => {
+            let asset = env.mint(spl_token::ID, 6, None).await;
+            let supply = params.amount + scenario.reloads();
+            env.mint_to(&parties.sender.pubkey(), &asset, supply).await;
+            asset
+        }
+        false => Asset::Native,
+    };
+    let parties = Parties { asset, ..parties };
+    let (sender, recipient) = (&parties.sender, &parties.recipient);
+
+    let mut ledger = Ledger {
+        deposited: params.amount,
+        refunded: 0,
+        payees_before: payees(env, &parties).await,
+    };
+    let stream = env
+        .create(sender, &recipient.pubkey(), asset, &params)
+        .await;
+
+    let mut ops = scenario.ops.clone();
+    // Drain whatever is left: resume, run past any stop time and withdraw everything
+    ops.extend([Op::Resume, Op::Warp(1_000_000_000), Op::Withdraw(None)]);
+
+    for op in ops {
+        let before = balances(env, &parties, &stream).await;
+        let result = match op {
+            Op::Warp(seconds) => {
+                let now = env.now().await;
+                env.warp(now + seconds).await;
+                Ok(())
+            }
+            Op::Withdraw(amount) => {
+                let ix =
+                    instructions::withdraw(&stream, &recipient.pubkey(), Some(&treasury), amount);
+                env.send(ix, &[recipient]).await
+            }
+            Op::Pause => {
+                let ix = instructions::pause(&stream, &sender.pubkey(), Some(&treasury));
+                env.send(ix, &[sender]).await
+            }
+            Op::Resume => {
+                let ix = instructions::resume(&stream, &sender.pubkey());
+                env.send(ix, &[sender]).await
+            }
+            Op::Reload(amount) => {
+                env.send(instructions::reload(&stream, amount), &[sender])
+                    .await
+            }
+            Op::Cancel => {
+                let ix = instructions::cancel(&stream, &sender.pubkey(), Some(&treasury));
+                env.send(ix, &[sender]).await
+            }
+        };
+        let after = balances(env, &parties, &stream).await;
+
+        // Only reloads take from the sender and only cancels give back to it
+        match (&op, result.is_ok()) {
+            (Op::Reload(amount), true) => {
+                assert_eq!(before.sender - after.sender, *amount, "reload");
+                ledger.deposited += amount;
+            }
+            (Op::Cancel, true) => ledger.refunded += after.sender - before.sender,
+            _ => assert_eq!(after.sender, before.sender, "{op:?} moved sender funds"),
+        }
+
+        let account = env.stream(&stream.address).await;
+        ledger.check(&account, after);
+    }
+
+    let account = env.stream(&stream.address).await;
+    assert_eq!(account.remaining_balance, 0, "funds stuck in the stream");
+    assert_eq!(
+        account.withdrawn + ledger.refunded,
+        account.deposit,
+        "withdrawn + refunded != deposit"
+    );
+}
+
+thread_local! {
+    static HARNESS: RefCell<Option<(tokio::runtime::Runtime, Env)>> = const { RefCell::new(None) };
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: std::env::var("PROPTEST_CASES")
+            .ok()
+            .and_then(|cases| cases.parse().ok())
+            .unwrap_or(32),
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn funds_are_conserved(scenario in scenario()) {
+        HARNESS.with(|harness| {
+            let mut harness = harness.borrow_mut();
+            let (runtime, env) = harness.get_or_insert_with(|| {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+                let env = runtime.block_on(Env::new());
+                (runtime, env)
+            });
+            runtime.block_on(run(env, scenario));
+        });
+    }
+}
@@ -0,0 +1,923 @@
+//! Native streams across time: before the start, mid-interval, paused, after the stop
+//! and after a reload, with the exact lamports of every party.
+
+mod common;
+
+use common::*;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use stream_client::{instructions, pda, Asset, Checkpoint, MyError, StreamPhase};
+use stream_contract::{
+    Cancelled, Deleted, Edited, Paused, Reloaded, Resumed, StreamCreated, Withdrawn,
+};
+
+#[tokio::test]
+async fn interval_stream_pays_by_the_clock() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let sender_before = env.lamports(&sender.pubkey()).await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+
+    // 100_000 every 10 seconds from T0 + 100 until T0 + 200
+    let params = params("payroll", 1_000_000, 10, 100_000, T0 + 100);
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+
+    assert_eq!(
+        env.lamports(&sender.pubkey()).await,
+        sender_before - 1_000_000 - stream_rent()
+    );
+    assert_eq!(
+        env.lamports(&stream.address).await,
+        stream_rent() + 1_000_000
+    );
+    let account = env.stream(&stream.address).await;
+    assert_eq!(account.start_time, T0 + 100);
+    assert_eq!(account.stop_time, T0 + 200);
+
+    let withdraw =
+        |amount| instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), amount);
+    let (withdraw_all, withdraw_some) = (withdraw(None), withdraw(Some(1)));
+
+    // Before the start
+    env.warp(T0 + 50).await;
+    env.fails(
+        withdraw_all.clone(),
+        &[&recipient],
+        MyError::StreamNotStarted,
+    )
+    .await;
+
+    // Two and a half intervals in, two have vested
+    env.warp(T0 + 125).await;
+    env.fails(
+        instructions::withdraw(
+            &stream,
+            &recipient.pubkey(),
+            Some(&env.treasury),
+            Some(200_001),
+        ),
+        &[&recipient],
+        MyError::AmountExceedsWithdrawable,
+    )
+    .await;
+    env.ok(withdraw_some, &[&recipient]).await;
+    env.ok(withdraw_all.clone(), &[&recipient]).await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 200_000
+    );
+
+    // Pausing pays out what has vested, five intervals
+    env.warp(T0 + 155).await;
+    env.ok(
+        instructions::pause(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+    )
+    .await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 500_000
+    );
+    let account = env.stream(&stream.address).await;
+    assert_eq!((account.time_left, account.paused_amount), (45, 500_000));
+
+    // Nothing vests while paused
+    env.warp(T0 + 180).await;
+    env.fails(withdraw_all.clone(), &[&recipient], MyError::StreamIsPaused)
+        .await;
+    assert_eq!(
+        env.stream(&stream.address)
+            .await
+            .status_at(T0 + 180)
+            .unwrap()
+            .phase,
+        StreamPhase::Paused
+    );
+
+    // The time left restarts from the resume
+    env.warp(T0 + 190).await;
+    env.ok(instructions::resume(&stream, &sender.pubkey()), &[&sender])
+        .await;
+    let account = env.stream(&stream.address).await;
+    assert_eq!(
+        (account.start_time, account.stop_time),
+        (T0 + 190, T0 + 235)
+    );
+
+    env.warp(T0 + 205).await;
+    env.ok(withdraw_all.clone(), &[&recipient]).await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 600_000
+    );
+
+    // After the stop everything left is withdrawable
+    env.warp(T0 + 300).await;
+    env.ok(withdraw_all.clone(), &[&recipient]).await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 1_000_000
+    );
+    assert_eq!(env.lamports(&stream.address).await, stream_rent());
+    env.fails(withdraw_all, &[&recipient], MyError::NothingToWithdraw)
+        .await;
+
+    // Deleting the emptied stream returns its rent
+    env.ok(instructions::delete(&stream), &[&sender]).await;
+    assert!(env.account(&stream.address).await.is_none());
+    assert_eq!(
+        env.lamports(&sender.pubkey()).await,
+        sender_before - 1_000_000
+    );
+}
+
+#[tokio::test]
+async fn stream_stops_when_its_last_interval_vests() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+
+    // 100_000 every 10 seconds, the eleventh interval pays the last 50_000
+    let mut params = params("tail", 1_050_000, 10, 100_000, 0);
+    params.start_now = true;
+    assert_eq!(params.duration, 110);
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    assert_eq!(env.stream(&stream.address).await.stop_time, T0 + 110);
+
+    let withdraw_all =
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None);
+    env.warp(T0 + 109).await;
+    env.ok(withdraw_all.clone(), &[&recipient]).await;
+    env.fails(
+        instructions::delete(&stream),
+        &[&sender],
+        MyError::StreamNotEmpty,
+    )
+    .await;
+
+    // Drained at its stop time, the stream can go
+    env.warp(T0 + 110).await;
+    env.ok(withdraw_all, &[&recipient]).await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 1_050_000
+    );
+    env.ok(instructions::delete(&stream), &[&sender]).await;
+    assert!(env.account(&stream.address).await.is_none());
+}
+
+#[tokio::test]
+async fn fees_too_small_to_open_the_treasury_are_waived() {
+    let mut env = Env::new().await;
+    // A treasury wallet that does not exist yet
+    let treasury = Pubkey::new_unique();
+    env.treasury = treasury;
+    env.set_fee(100).await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+
+    let mut params = params("dust", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+
+    // 1% of 100_000 is short of the rent of the wallet, the recipient keeps all of it
+    env.warp(T0 + 100).await;
+    env.ok(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&treasury), None),
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 100_000
+    );
+    assert_eq!(env.lamports(&treasury).await, 0);
+    assert_eq!(env.stream(&stream.address).await.protocol_fee_paid, 0);
+}
+
+#[tokio::test]
+async fn cancel_pays_fees_and_refunds_the_sender() {
+    let mut env = Env::new().await;
+    env.set_fee(100).await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let partner = env.user().await;
+
+    let mut params = params("fees", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    params.partner = Some(partner.pubkey());
+    params.partner_fee_bps = 50;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+
+    let sender_before = env.lamports(&sender.pubkey()).await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+    let partner_before = env.lamports(&partner.pubkey()).await;
+    let treasury = env.treasury;
+    let treasury_before = env.lamports(&treasury).await;
+
+    // 250_000 vested: 1% to the treasury, 0.5% to the partner, the rest refunded
+    env.warp(T0 + 250).await;
+    env.ok(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+    )
+    .await;
+
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 246_250
+    );
+    assert_eq!(env.lamports(&treasury).await, treasury_before + 2_500);
+    assert_eq!(
+        env.lamports(&partner.pubkey()).await,
+        partner_before + 1_250
+    );
+    assert_eq!(
+        env.lamports(&sender.pubkey()).await,
+        sender_before + 750_000
+    );
+
+    let account = env.stream(&stream.address).await;
+    assert!(account.is_cancelled());
+    assert_eq!(
+        (
+            account.withdrawn,
+            account.protocol_fee_paid,
+            account.partner_fee_paid
+        ),
+        (250_000, 2_500, 1_250)
+    );
+    assert_eq!(account.remaining_balance, 0);
+
+    env.warp(T0 + 300).await;
+    env.fails(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None),
+        &[&recipient],
+        MyError::StreamAlreadyCancelled,
+    )
+    .await;
+    env.fails(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+        MyError::StreamAlreadyCancelled,
+    )
+    .await;
+    env.ok(instructions::delete(&stream), &[&sender]).await;
+}
+
+#[tokio::test]
+async fn cancel_before_the_start_refunds_everything() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+
+    let params = params("early", 500_000, 5, 5_000, T0 + 1_000);
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    let sender_before = env.lamports(&sender.pubkey()).await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+
+    env.warp(T0 + 10).await;
+    env.fails(
+        instructions::pause(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+        MyError::StreamNotStarted,
+    )
+    .await;
+    env.ok(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+    )
+    .await;
+
+    assert_eq!(
+        env.lamports(&sender.pubkey()).await,
+        sender_before + 500_000
+    );
+    assert_eq!(env.lamports(&recipient.pubkey()).await, recipient_before);
+}
+
+#[tokio::test]
+async fn reload_extends_an_infinite_stream() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+
+    // 1_000 every 10 seconds, until T0 + 1_000
+    let mut params = params("infinite", 100_000, 10, 1_000, 0);
+    params.start_now = true;
+    params.is_infinite = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    let withdraw = instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None);
+
+    env.warp(T0 + 505).await;
+    env.ok(withdraw.clone(), &[&recipient]).await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 50_000
+    );
+
+    // 50_000 more lasts another 500 seconds
+    let sender_before = env.lamports(&sender.pubkey()).await;
+    env.ok(instructions::reload(&stream, 50_000), &[&sender])
+        .await;
+    assert_eq!(env.lamports(&sender.pubkey()).await, sender_before - 50_000);
+    let account = env.stream(&stream.address).await;
+    assert_eq!(
+        (
+            account.deposit,
+            account.remaining_balance,
+            account.stop_time
+        ),
+        (150_000, 100_000, T0 + 1_500)
+    );
+
+    // Past the old stop the stream keeps paying
+    env.warp(T0 + 1_200).await;
+    env.ok(withdraw.clone(), &[&recipient]).await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 120_000
+    );
+
+    // A reload while paused extends the time left instead
+    env.ok(
+        instructions::pause(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+    )
+    .await;
+    env.ok(instructions::reload(&stream, 10_000), &[&sender])
+        .await;
+    let account = env.stream(&stream.address).await;
+    assert_eq!(account.time_left, 300 + 100);
+
+    env.warp(T0 + 1_300).await;
+    env.ok(instructions::resume(&stream, &sender.pubkey()), &[&sender])
+        .await;
+    env.warp(T0 + 2_000).await;
+    env.ok(withdraw, &[&recipient]).await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 160_000
+    );
+    assert_eq!(env.lamports(&stream.address).await, stream_rent());
+}
+
+#[tokio::test]
+async fn reloading_a_depleted_stream_starts_it_again() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+
+    // 1_000 every 10 seconds, until T0 + 100
+    let mut params = params("depleted", 10_000, 10, 1_000, 0);
+    params.start_now = true;
+    params.is_infinite = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    let withdraw = instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None);
+
+    env.warp(T0 + 500).await;
+    env.ok(withdraw.clone(), &[&recipient]).await;
+    env.ok(instructions::reload(&stream, 5_000), &[&sender])
+        .await;
+
+    // The top-up streams from the reload on, none of it is withdrawable yet
+    let account = env.stream(&stream.address).await;
+    assert_eq!(
+        (account.start_time, account.stop_time),
+        (T0 + 500, T0 + 550)
+    );
+    assert_eq!(account.withdrawable_at(T0 + 500).unwrap(), 0);
+    env.fails(withdraw.clone(), &[&recipient], MyError::NothingToWithdraw)
+        .await;
+
+    env.warp(T0 + 520).await;
+    env.ok(withdraw, &[&recipient]).await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 12_000
+    );
+}
+
+#[tokio::test]
+async fn withdraw_to_pays_another_wallet() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let destination = env.user().await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+    let destination_before = env.lamports(&destination.pubkey()).await;
+
+    let mut params = params("elsewhere", 60_000, 60, 1_000, 0);
+    params.start_now = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+
+    env.warp(T0 + 600).await;
+    let ix = instructions::withdraw_to(
+        &stream,
+        &destination.pubkey(),
+        Some(&env.treasury),
+        Some(4_000),
+    );
+    env.ok(ix, &[&recipient]).await;
+    env.ok(
+        instructions::withdraw_to(&stream, &destination.pubkey(), Some(&env.treasury), None),
+        &[&recipient],
+    )
+    .await;
+
+    assert_eq!(
+        env.lamports(&destination.pubkey()).await,
+        destination_before + 10_000
+    );
+    assert_eq!(env.lamports(&recipient.pubkey()).await, recipient_before);
+
+    // The stream itself is not a valid destination
+    env.warp(T0 + 700).await;
+    env.fails(
+        instructions::withdraw_to(&stream, &stream.address, Some(&env.treasury), None),
+        &[&recipient],
+        MyError::InvalidDestination,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn edit_reschedules_the_unvested_balance() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+
+    // 10_000 every 10 seconds until T0 + 1_000
+    let mut params = params("edited", 1_000_000, 10, 10_000, 0);
+    params.start_now = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+
+    // The 100_000 vested is paid, the other 900_000 is streamed twice as fast
+    env.warp(T0 + 100).await;
+    let ix = instructions::edit(
+        &stream,
+        &sender.pubkey(),
+        Some(&env.treasury),
+        "faster".to_string(),
+        10,
+        20_000,
+        450,
+    );
+    env.ok(ix, &[&sender]).await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 100_000
+    );
+    let account = env.stream(&stream.address).await;
+    assert_eq!(account.stream_title, "faster");
+    assert_eq!(
+        (account.start_time, account.stop_time),
+        (T0 + 100, T0 + 550)
+    );
+
+    env.warp(T0 + 200).await;
+    env.ok(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None),
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 300_000
+    );
+
+    // After the stop nothing can be settled anymore
+    env.warp(T0 + 600).await;
+    env.fails(
+        instructions::pause(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+        MyError::StreamAlreadyEnded,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn cliff_locks_until_its_date() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+
+    // 200_000 at T0 + 100, then 1_000 a second until T0 + 800
+    let mut params = params("cliff", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    params.cliff_amount = 200_000;
+    params.cliff_time = T0 + 100;
+    params.duration = 800;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    let withdraw = instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None);
+
+    env.warp(T0 + 50).await;
+    env.fails(withdraw.clone(), &[&recipient], MyError::CliffNotReached)
+        .await;
+
+    // Pausing before the cliff pays nothing and keeps the time left to it
+    env.ok(
+        instructions::pause(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+    )
+    .await;
+    assert_eq!(env.lamports(&recipient.pubkey()).await, recipient_before);
+    assert_eq!(env.stream(&stream.address).await.cliff_time_left, 50);
+
+    env.warp(T0 + 150).await;
+    env.ok(instructions::resume(&stream, &sender.pubkey()), &[&sender])
+        .await;
+    assert_eq!(env.stream(&stream.address).await.cliff_time, T0 + 200);
+
+    env.warp(T0 + 210).await;
+    env.ok(withdraw, &[&recipient]).await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 250_000 + 60_000
+    );
+}
+
+#[tokio::test]
+async fn get_stream_status_returns_the_view() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+
+    let params = params("status", 1_000_000, 10, 100_000, T0 + 100);
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    env.warp(T0 + 145).await;
+
+    let view = env.status(&stream.address).await;
+    assert_eq!(
+        view,
+        env.stream(&stream.address)
+            .await
+            .status_at(T0 + 145)
+            .unwrap()
+    );
+    assert_eq!(view.phase, StreamPhase::Streaming);
+    assert_eq!((view.withdrawable, view.refundable), (400_000, 600_000));
+}
+
+#[tokio::test]
+async fn percentage_cliff_is_a_share_of_the_deposit() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+
+    // 25% at the start, then 7_500 a second until T0 + 100
+    let mut params = params("percent", 1_000_000, 1, 7_500, 0);
+    params.start_now = true;
+    params.is_cliff_percent = true;
+    params.cliff_amount = 2_500;
+    params.duration = 100;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    let account = env.stream(&stream.address).await;
+    assert!(account.is_cliff_percent);
+    assert_eq!(
+        (account.cliff_amount, account.deposit),
+        (250_000, 1_000_000)
+    );
+
+    env.warp(T0 + 40).await;
+    env.ok(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None),
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 250_000 + 300_000
+    );
+}
+
+#[tokio::test]
+async fn every_transition_emits_an_event() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+
+    // 1_000 a second until T0 + 1_000
+    let mut params = params("observed", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    params.is_infinite = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+
+    env.warp(T0 + 100).await;
+    env.ok(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&treasury), None),
+        &[&recipient],
+    )
+    .await;
+    env.warp(T0 + 200).await;
+    env.ok(
+        instructions::pause(&stream, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+    )
+    .await;
+    env.warp(T0 + 300).await;
+    env.ok(instructions::resume(&stream, &sender.pubkey()), &[&sender])
+        .await;
+    env.ok(instructions::reload(&stream, 50_000), &[&sender])
+        .await;
+    env.warp(T0 + 400).await;
+    let ix = instructions::edit(
+        &stream,
+        &sender.pubkey(),
+        Some(&treasury),
+        String::new(),
+        1,
+        1_000,
+        750,
+    );
+    env.ok(ix, &[&sender]).await;
+    env.ok(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+    )
+    .await;
+    env.ok(instructions::delete(&stream), &[&sender]).await;
+
+    // Other tests emit events too, only the ones of this stream count
+    macro_rules! of_stream {
+        ($event:ty) => {{
+            let mut events = events::<$event>();
+            events.retain(|e| e.stream == stream.address);
+            assert_eq!(events.len(), 1, "{}", stringify!($event));
+            events.remove(0)
+        }};
+    }
+
+    let created = of_stream!(StreamCreated);
+    assert_eq!(
+        (created.sender, created.recipient),
+        (sender.pubkey(), recipient.pubkey())
+    );
+    assert_eq!(
+        (created.deposit, created.stop_time),
+        (1_000_000, T0 + 1_000)
+    );
+    let withdrawn = of_stream!(Withdrawn);
+    assert_eq!((withdrawn.amount, withdrawn.timestamp), (100_000, T0 + 100));
+    let paused = of_stream!(Paused);
+    assert_eq!((paused.amount, paused.time_left), (100_000, 800));
+    let resumed = of_stream!(Resumed);
+    assert_eq!(resumed.stop_time, T0 + 1_100);
+    let reloaded = of_stream!(Reloaded);
+    assert_eq!((reloaded.amount, reloaded.deposit), (50_000, 1_050_000));
+    let edited = of_stream!(Edited);
+    assert_eq!(edited.settled, 100_000);
+    let cancelled = of_stream!(Cancelled);
+    assert_eq!(
+        (cancelled.recipient_amount, cancelled.sender_amount),
+        (0, 750_000)
+    );
+    let deleted = of_stream!(Deleted);
+    assert_eq!(deleted.actor, sender.pubkey());
+}
+
+#[tokio::test]
+async fn a_new_admin_halts_and_restarts_the_program() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let admin = env.admin.insecure_clone();
+    let successor = env.user().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+
+    let mut params = params("halted", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+    let withdraw = instructions::withdraw(&stream, &recipient.pubkey(), Some(&treasury), None);
+    let halt = |admin: &Pubkey, is_halted| {
+        instructions::update_config(admin, is_halted, Vec::new(), 0, &treasury)
+    };
+
+    // The admin only changes once the proposed one accepts
+    env.ok(
+        instructions::transfer_admin(&admin.pubkey(), &successor.pubkey()),
+        &[&admin],
+    )
+    .await;
+    env.ok(halt(&admin.pubkey(), false), &[&admin]).await;
+    env.ok(
+        instructions::accept_admin(&successor.pubkey()),
+        &[&successor],
+    )
+    .await;
+    env.fails(
+        halt(&admin.pubkey(), true),
+        &[&admin],
+        MyError::NotAuthorized,
+    )
+    .await;
+
+    env.ok(halt(&successor.pubkey(), true), &[&successor]).await;
+    env.warp(T0 + 100).await;
+    env.fails(withdraw.clone(), &[&recipient], MyError::ProgramHalted)
+        .await;
+
+    // Nothing is lost while halted, the stream kept vesting
+    env.ok(halt(&successor.pubkey(), false), &[&successor])
+        .await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+    env.ok(withdraw, &[&recipient]).await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 100_000
+    );
+}
+
+#[tokio::test]
+async fn cancelling_a_step_stream_refunds_the_locked_steps() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+
+    // A quarter up front, then a quarter every 100 seconds
+    let mut params = params("quarters", 400_000, 1, 1, 0);
+    params.start_now = true;
+    let checkpoints = [(0, 100_000), (100, 200_000), (200, 300_000), (300, 400_000)]
+        .map(|(at, unlocked)| Checkpoint {
+            timestamp: T0 + at,
+            cumulative_unlocked: unlocked,
+        })
+        .to_vec();
+    let ix = instructions::create_step_stream(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &Asset::Native,
+        &params,
+        checkpoints,
+    );
+    env.ok(ix, &[&sender]).await;
+    let address = pda::stream_address(&params.stream_id, &sender.pubkey());
+    let stream = env.keys(&address, Asset::Native).await;
+
+    // Steps follow their table, they cannot be rescheduled
+    let ix = instructions::edit(
+        &stream,
+        &sender.pubkey(),
+        Some(&treasury),
+        String::new(),
+        1,
+        1_000,
+        400,
+    );
+    env.fails(ix, &[&sender], MyError::ScheduleNotEditable)
+        .await;
+
+    env.warp(T0 + 150).await;
+    let sender_before = env.lamports(&sender.pubkey()).await;
+    env.ok(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+    )
+    .await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 200_000
+    );
+    assert_eq!(
+        env.lamports(&sender.pubkey()).await,
+        sender_before + 200_000
+    );
+}
+
+#[tokio::test]
+async fn linear_stream_picks_up_where_it_was_paused() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let recipient_before = env.lamports(&recipient.pubkey()).await;
+
+    // 1_000 a second until T0 + 1_000, not in steps of the 100 second interval
+    let mut params = params("smooth", 1_000_000, 100, 100_000, 0);
+    params.start_now = true;
+    params.is_linear = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), Asset::Native, &params)
+        .await;
+
+    env.warp(T0 + 250).await;
+    env.ok(
+        instructions::pause(&stream, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+    )
+    .await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 250_000
+    );
+
+    // The 750 seconds left run from the resume
+    env.warp(T0 + 400).await;
+    env.ok(instructions::resume(&stream, &sender.pubkey()), &[&sender])
+        .await;
+    env.warp(T0 + 775).await;
+    let account = env.stream(&stream.address).await;
+    assert_eq!(account.stop_time, T0 + 1_150);
+    assert_eq!(account.withdrawable_at(T0 + 775).unwrap(), 375_000);
+    env.ok(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&treasury), None),
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(
+        env.lamports(&recipient.pubkey()).await,
+        recipient_before + 625_000
+    );
+}
+
+#[tokio::test]
+async fn get_stream_status_follows_pause_end_and_cancel() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+
+    let mut streams = Vec::new();
+    for id in ["paused", "ended", "cancelled"] {
+        let mut params = params(id, 100_000, 1, 1_000, 0);
+        params.start_now = true;
+        streams.push(
+            env.create(&sender, &recipient.pubkey(), Asset::Native, &params)
+                .await,
+        );
+    }
+    let [paused, ended, cancelled] = &streams[..] else {
+        unreachable!()
+    };
+
+    env.warp(T0 + 30).await;
+    env.ok(
+        instructions::pause(paused, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+    )
+    .await;
+    env.ok(
+        instructions::cancel(cancelled, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+    )
+    .await;
+
+    // Paused, the stream keeps its balance and pays nothing more
+    env.warp(T0 + 150).await;
+    let view = env.status(&paused.address).await;
+    assert_eq!(view.phase, StreamPhase::Paused);
+    assert_eq!(
+        (view.withdrawable, view.withdrawn, view.refundable),
+        (0, 30_000, 70_000)
+    );
+
+    let view = env.status(&ended.address).await;
+    assert_eq!(view.phase, StreamPhase::Ended);
+    assert_eq!((view.withdrawable, view.refundable), (100_000, 0));
+
+    let view = env.status(&cancelled.address).await;
+    assert_eq!(view.phase, StreamPhase::Cancelled);
+    assert_eq!(
+        (view.withdrawable, view.remaining_balance, view.timestamp),
+        (0, 0, T0 + 150)
+    );
+}
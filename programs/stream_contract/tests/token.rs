@@ -0,0 +1,966 @@
+//! Token streams under the Token and Token-2022 programs, every schedule kind and the batch
+//! instructions, with the exact token balances of every party.
+
+mod common;
+
+use common::*;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use stream_client::{
+    accounts, instructions, pda, Asset, Checkpoint, MyError, SplitRecipient, StreamRef,
+};
+use stream_contract::Withdrawn;
+
+#[tokio::test]
+async fn token_stream_pays_fees_and_refunds() {
+    let mut env = Env::new().await;
+    env.set_fee(100).await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let partner = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_000_000).await;
+
+    // 100_000 every 10 seconds from T0 + 100 until T0 + 200
+    let mut params = params("grant", 1_000_000, 10, 100_000, T0 + 100);
+    params.partner = Some(partner.pubkey());
+    params.partner_fee_bps = 50;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+    assert_eq!(env.tokens(&sender.pubkey(), &asset).await, 0);
+    assert_eq!(env.tokens(&stream.address, &asset).await, 1_000_000);
+
+    let withdraw = instructions::withdraw(&stream, &recipient.pubkey(), Some(&treasury), None);
+    env.warp(T0 + 50).await;
+    env.fails(withdraw.clone(), &[&recipient], MyError::StreamNotStarted)
+        .await;
+
+    // 1% to the treasury and 0.5% to the partner on every payout
+    env.warp(T0 + 130).await;
+    env.ok(withdraw.clone(), &[&recipient]).await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 295_500);
+    assert_eq!(env.tokens(&treasury, &asset).await, 3_000);
+    assert_eq!(env.tokens(&partner.pubkey(), &asset).await, 1_500);
+    assert_eq!(env.tokens(&stream.address, &asset).await, 700_000);
+
+    env.warp(T0 + 145).await;
+    env.ok(
+        instructions::pause(&stream, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+    )
+    .await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 394_000);
+
+    env.warp(T0 + 160).await;
+    env.ok(instructions::resume(&stream, &sender.pubkey()), &[&sender])
+        .await;
+    assert_eq!(env.stream(&stream.address).await.stop_time, T0 + 215);
+
+    // Two more intervals vested since the resume
+    env.warp(T0 + 185).await;
+    env.ok(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+    )
+    .await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 591_000);
+    assert_eq!(env.tokens(&treasury, &asset).await, 6_000);
+    assert_eq!(env.tokens(&partner.pubkey(), &asset).await, 3_000);
+    assert_eq!(env.tokens(&sender.pubkey(), &asset).await, 400_000);
+    assert_eq!(env.tokens(&stream.address, &asset).await, 0);
+
+    env.ok(instructions::delete(&stream), &[&sender]).await;
+    assert!(env.account(&stream.address).await.is_none());
+}
+
+#[tokio::test]
+async fn transfer_fees_are_withheld_from_every_transfer() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token_2022::ID, 9, Some((100, u64::MAX))).await;
+    env.mint_to(&sender.pubkey(), &asset, 2_000_000).await;
+
+    let mut params = params("fee-mint", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+
+    // The deposit is grossed up so that the whole amount reaches the vault
+    let vault_withheld = env.withheld(&stream.address, &asset).await;
+    assert!(vault_withheld > 0);
+    assert_eq!(env.tokens(&stream.address, &asset).await, 1_000_000);
+    assert_eq!(
+        env.tokens(&sender.pubkey(), &asset).await,
+        2_000_000 - 1_000_000 - vault_withheld
+    );
+    assert_eq!(env.stream(&stream.address).await.deposit, 1_000_000);
+
+    // Payouts lose 1% on the way to the recipient
+    env.warp(T0 + 100).await;
+    env.ok(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None),
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 99_000);
+    assert_eq!(env.withheld(&recipient.pubkey(), &asset).await, 1_000);
+
+    let sender_before = env.tokens(&sender.pubkey(), &asset).await;
+    env.warp(T0 + 400).await;
+    env.ok(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+    )
+    .await;
+    assert_eq!(
+        env.tokens(&recipient.pubkey(), &asset).await,
+        99_000 + 297_000
+    );
+    assert_eq!(env.withheld(&recipient.pubkey(), &asset).await, 4_000);
+    assert_eq!(
+        env.tokens(&sender.pubkey(), &asset).await,
+        sender_before + 594_000
+    );
+    assert_eq!(env.withheld(&sender.pubkey(), &asset).await, 6_000);
+
+    let account = env.stream(&stream.address).await;
+    assert_eq!((account.withdrawn, account.remaining_balance), (400_000, 0));
+}
+
+#[tokio::test]
+async fn token_2022_stream_is_reloaded() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token_2022::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 150_000).await;
+
+    // 1_000 every 10 seconds until T0 + 1_000
+    let mut params = params("topped-up", 100_000, 10, 1_000, 0);
+    params.start_now = true;
+    params.is_infinite = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+
+    env.warp(T0 + 500).await;
+    env.ok(instructions::reload(&stream, 50_000), &[&sender])
+        .await;
+    assert_eq!(env.tokens(&sender.pubkey(), &asset).await, 0);
+    assert_eq!(env.tokens(&stream.address, &asset).await, 150_000);
+    assert_eq!(env.stream(&stream.address).await.stop_time, T0 + 1_500);
+
+    env.warp(T0 + 1_250).await;
+    env.ok(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None),
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 125_000);
+
+    env.warp(T0 + 1_500).await;
+    env.ok(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None),
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 150_000);
+    assert_eq!(env.tokens(&stream.address, &asset).await, 0);
+}
+
+#[tokio::test]
+async fn step_stream_unlocks_at_its_checkpoints() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_000_000).await;
+
+    let mut params = params("vesting", 1_000_000, 1, 1, 0);
+    params.start_now = true;
+    let checkpoints = [(100, 100_000), (200, 400_000), (300, 1_000_000)]
+        .map(|(at, unlocked)| Checkpoint {
+            timestamp: T0 + at,
+            cumulative_unlocked: unlocked,
+        })
+        .to_vec();
+    let ix = instructions::create_step_stream(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &asset,
+        &params,
+        checkpoints,
+    );
+    env.ok(ix, &[&sender]).await;
+    let address = pda::stream_address(&params.stream_id, &sender.pubkey());
+    let stream = env.keys(&address, asset).await;
+    let withdraw = instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None);
+
+    env.warp(T0 + 50).await;
+    env.fails(withdraw.clone(), &[&recipient], MyError::NothingToWithdraw)
+        .await;
+
+    env.warp(T0 + 150).await;
+    env.ok(withdraw.clone(), &[&recipient]).await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 100_000);
+
+    env.warp(T0 + 250).await;
+    env.ok(withdraw.clone(), &[&recipient]).await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 400_000);
+
+    // The last checkpoint moves back by the 40 seconds spent paused
+    env.warp(T0 + 260).await;
+    env.ok(
+        instructions::pause(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+    )
+    .await;
+    env.warp(T0 + 300).await;
+    env.ok(instructions::resume(&stream, &sender.pubkey()), &[&sender])
+        .await;
+    let account = env.stream(&stream.address).await;
+    assert_eq!(account.stop_time, T0 + 340);
+    assert_eq!(account.checkpoints[2].timestamp, T0 + 340);
+
+    env.warp(T0 + 320).await;
+    env.fails(withdraw.clone(), &[&recipient], MyError::NothingToWithdraw)
+        .await;
+
+    env.warp(T0 + 340).await;
+    env.ok(withdraw, &[&recipient]).await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 1_000_000);
+    assert_eq!(env.tokens(&stream.address, &asset).await, 0);
+}
+
+#[tokio::test]
+async fn fee_free_payouts_leave_the_treasury_out() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_000_000).await;
+
+    let mut params = params("free", 1_000_000, 10, 100_000, 0);
+    params.start_now = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+
+    env.warp(T0 + 20).await;
+    env.ok(
+        instructions::withdraw(&stream, &recipient.pubkey(), None, None),
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 200_000);
+
+    // Nobody paid to open a token account for the treasury
+    let Asset::Token {
+        mint,
+        token_program,
+    } = asset
+    else {
+        unreachable!()
+    };
+    let treasury_tokens = pda::token_account_address(&env.treasury, &mint, &token_program);
+    assert!(env.account(&treasury_tokens).await.is_none());
+
+    // Once a fee is charged the treasury has to be passed
+    env.set_fee(100).await;
+    env.warp(T0 + 40).await;
+    env.fails(
+        instructions::withdraw(&stream, &recipient.pubkey(), None, None),
+        &[&recipient],
+        MyError::InvalidTreasury,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn partner_accounts_are_only_needed_for_a_partner_fee() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let partner = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 2_000_000).await;
+    let Asset::Token {
+        mint,
+        token_program,
+    } = asset
+    else {
+        unreachable!()
+    };
+
+    let mut params = params("solo", 1_000_000, 10, 100_000, 0);
+    params.start_now = true;
+    let solo = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+    assert_eq!(solo.partner, None);
+
+    params.stream_id = "shared".to_string();
+    params.partner = Some(partner.pubkey());
+    params.partner_fee_bps = 50;
+    let shared = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+    assert_eq!(shared.partner, Some(partner.pubkey()));
+
+    env.warp(T0 + 20).await;
+    env.ok(
+        instructions::withdraw(&solo, &recipient.pubkey(), None, None),
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 200_000);
+    let nobody = pda::token_account_address(&Pubkey::default(), &mint, &token_program);
+    assert!(env.account(&nobody).await.is_none());
+
+    // Leaving out the partner of a stream that pays one fails
+    let mut without_partner = shared.clone();
+    without_partner.partner = None;
+    env.fails(
+        instructions::withdraw(&without_partner, &recipient.pubkey(), None, None),
+        &[&recipient],
+        MyError::IncorrectPartner,
+    )
+    .await;
+
+    env.ok(
+        instructions::withdraw(&shared, &recipient.pubkey(), None, None),
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(env.tokens(&partner.pubkey(), &asset).await, 1_000);
+}
+
+#[tokio::test]
+async fn linear_stream_vests_every_second() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_500_000).await;
+
+    // 1_000 a second until T0 + 1_000
+    let mut params = params("linear", 1_000_000, 100, 100_000, 0);
+    params.start_now = true;
+    params.is_infinite = true;
+    params.is_linear = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+    let withdraw = instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None);
+
+    env.warp(T0 + 250).await;
+    env.ok(withdraw.clone(), &[&recipient]).await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 250_000);
+
+    // The top-up only vests from the reload on, at the same rate
+    env.warp(T0 + 400).await;
+    env.ok(instructions::reload(&stream, 500_000), &[&sender])
+        .await;
+    let account = env.stream(&stream.address).await;
+    assert_eq!(
+        (account.start_time, account.stop_time),
+        (T0 + 400, T0 + 1_500)
+    );
+    assert_eq!(account.paused_amount, 400_000);
+
+    env.warp(T0 + 950).await;
+    env.ok(withdraw.clone(), &[&recipient]).await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 950_000);
+
+    env.warp(T0 + 2_000).await;
+    env.ok(withdraw, &[&recipient]).await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 1_500_000);
+}
+
+#[tokio::test]
+async fn split_stream_pays_weighted_shares() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let first = env.user().await;
+    let second = env.user().await;
+    let stranger = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_000_000).await;
+
+    // 100_000 every 10 seconds, 60% to the first recipient and 40% to the second
+    let mut params = params("team", 1_000_000, 10, 100_000, 0);
+    params.start_now = true;
+    let recipients = vec![
+        SplitRecipient {
+            recipient: first.pubkey(),
+            weight_bps: 6_000,
+        },
+        SplitRecipient {
+            recipient: second.pubkey(),
+            weight_bps: 4_000,
+        },
+    ];
+    let ix = instructions::create_split_stream(&sender.pubkey(), &asset, &params, recipients);
+    env.ok(ix, &[&sender]).await;
+    let address = pda::stream_address(&params.stream_id, &sender.pubkey());
+    let stream = env.keys(&address, asset).await;
+    assert_eq!(stream.recipient, pda::split_address(&address));
+
+    env.warp(T0 + 55).await;
+    env.ok(
+        instructions::withdraw_share(&stream, &first.pubkey(), Some(&env.treasury)),
+        &[&first],
+    )
+    .await;
+    assert_eq!(env.tokens(&first.pubkey(), &asset).await, 300_000);
+    env.fails(
+        instructions::withdraw_share(&stream, &first.pubkey(), Some(&env.treasury)),
+        &[&first],
+        MyError::NothingToWithdraw,
+    )
+    .await;
+    env.fails(
+        instructions::withdraw_share(&stream, &stranger.pubkey(), Some(&env.treasury)),
+        &[&stranger],
+        MyError::NotInSplit,
+    )
+    .await;
+
+    // Cancelling refunds the unvested part and keeps the vested part for the recipients
+    env.warp(T0 + 75).await;
+    env.ok(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&env.treasury)),
+        &[&sender],
+    )
+    .await;
+    assert_eq!(env.tokens(&sender.pubkey(), &asset).await, 300_000);
+    assert_eq!(env.tokens(&stream.address, &asset).await, 400_000);
+
+    env.warp(T0 + 1_000).await;
+    for recipient in [&first, &second] {
+        env.ok(
+            instructions::withdraw_share(&stream, &recipient.pubkey(), Some(&env.treasury)),
+            &[recipient],
+        )
+        .await;
+    }
+    assert_eq!(env.tokens(&first.pubkey(), &asset).await, 420_000);
+    assert_eq!(env.tokens(&second.pubkey(), &asset).await, 280_000);
+    assert_eq!(env.tokens(&stream.address, &asset).await, 0);
+
+    let split = env.split(&address).await;
+    assert_eq!(
+        split.shares.iter().map(|s| s.withdrawn).collect::<Vec<_>>(),
+        [420_000, 280_000]
+    );
+}
+
+#[tokio::test]
+async fn drained_infinite_split_stream_can_be_reloaded() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipients = [env.user().await, env.user().await, env.user().await];
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 2_000).await;
+
+    // Thirds of 1_000 leave a token of rounding dust, which only a cancel hands out
+    let mut params = params("pool", 1_000, 10, 100, 0);
+    params.start_now = true;
+    params.is_infinite = true;
+    let shares = recipients
+        .iter()
+        .zip([3_333, 3_333, 3_334])
+        .map(|(recipient, weight_bps)| SplitRecipient {
+            recipient: recipient.pubkey(),
+            weight_bps,
+        })
+        .collect();
+    let ix = instructions::create_split_stream(&sender.pubkey(), &asset, &params, shares);
+    env.ok(ix, &[&sender]).await;
+    let address = pda::stream_address(&params.stream_id, &sender.pubkey());
+    let stream = env.keys(&address, asset).await;
+    let treasury = env.treasury;
+    let withdraw = |recipient: &Keypair| {
+        instructions::withdraw_share(&stream, &recipient.pubkey(), Some(&treasury))
+    };
+
+    env.warp(T0 + 100).await;
+    for recipient in &recipients {
+        env.ok(withdraw(recipient), &[recipient]).await;
+    }
+    assert_eq!(env.tokens(&stream.address, &asset).await, 1);
+
+    env.ok(instructions::reload(&stream, 1_000), &[&sender])
+        .await;
+    env.fails(
+        withdraw(&recipients[2]),
+        &[&recipients[2]],
+        MyError::NothingToWithdraw,
+    )
+    .await;
+
+    env.warp(T0 + 200).await;
+    for recipient in &recipients {
+        env.ok(withdraw(recipient), &[recipient]).await;
+    }
+    env.ok(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+    )
+    .await;
+    for recipient in &recipients[1..] {
+        env.ok(withdraw(recipient), &[recipient]).await;
+    }
+    assert_eq!(env.tokens(&stream.address, &asset).await, 0);
+
+    let split = env.split(&address).await;
+    assert_eq!(
+        split.shares.iter().map(|s| s.withdrawn).collect::<Vec<_>>(),
+        [666, 667, 667]
+    );
+}
+
+#[tokio::test]
+async fn batches_create_and_withdraw_many_streams() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token_2022::ID, 6, None).await;
+    let Asset::Token {
+        mint,
+        token_program,
+    } = asset
+    else {
+        unreachable!()
+    };
+    env.mint_to(&sender.pubkey(), &asset, 300_000).await;
+
+    let mut running = params("running", 100_000, 1, 1_000, 0);
+    running.start_now = true;
+    let later = params("later", 200_000, 1, 1_000, T0 + 500);
+    let ix = instructions::create_streams_batch(
+        &sender.pubkey(),
+        &mint,
+        &token_program,
+        &[(recipient.pubkey(), running), (recipient.pubkey(), later)],
+    );
+    env.ok(ix, &[&sender]).await;
+    assert_eq!(env.tokens(&sender.pubkey(), &asset).await, 0);
+
+    let mut streams = Vec::new();
+    for id in ["running", "later"] {
+        let address = pda::stream_address(id, &sender.pubkey());
+        streams.push(env.keys(&address, asset).await);
+    }
+    assert_eq!(env.tokens(&streams[0].address, &asset).await, 100_000);
+    assert_eq!(env.tokens(&streams[1].address, &asset).await, 200_000);
+
+    // The stream that has not started yet is skipped
+    env.token_account(&recipient.pubkey(), &asset).await;
+    env.token_account(&treasury, &asset).await;
+    env.warp(T0 + 50).await;
+    let ix = instructions::withdraw_batch(
+        &recipient.pubkey(),
+        &token_program,
+        Some(&treasury),
+        &streams,
+    );
+    env.ok(ix.clone(), &[&recipient]).await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 50_000);
+    assert_eq!(env.tokens(&streams[1].address, &asset).await, 200_000);
+
+    env.warp(T0 + 600).await;
+    env.ok(ix, &[&recipient]).await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 200_000);
+    assert_eq!(env.stream(&streams[1].address).await.withdrawn, 100_000);
+}
+
+#[tokio::test]
+async fn batch_creation_survives_accounts_created_in_advance() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    let Asset::Token {
+        mint,
+        token_program,
+    } = asset
+    else {
+        unreachable!()
+    };
+    env.mint_to(&sender.pubkey(), &asset, 300_000).await;
+
+    // Anyone can send lamports to the next stream PDA or open the ATA of its vault
+    let funded = pda::stream_address("funded", &sender.pubkey());
+    let opened = pda::stream_address("opened", &sender.pubkey());
+    env.fund(&funded, Rent::default().minimum_balance(0)).await;
+    env.token_account(&opened, &asset).await;
+
+    let streams = [
+        (
+            recipient.pubkey(),
+            params("funded", 100_000, 1, 1_000, T0 + 10),
+        ),
+        (
+            recipient.pubkey(),
+            params("opened", 200_000, 1, 1_000, T0 + 10),
+        ),
+    ];
+    let ix = instructions::create_streams_batch(&sender.pubkey(), &mint, &token_program, &streams);
+    env.ok(ix, &[&sender]).await;
+
+    assert_eq!(env.lamports(&funded).await, stream_rent());
+    assert_eq!(env.stream(&funded).await.remaining_balance, 100_000);
+    assert_eq!(env.tokens(&opened, &asset).await, 200_000);
+    assert_eq!(env.stream(&opened).await.remaining_balance, 200_000);
+}
+
+#[tokio::test]
+async fn edit_by_decides_who_reschedules() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_000_000).await;
+
+    // 10_000 every 10 seconds until T0 + 1_000, only the recipient edits
+    let mut params = params("renegotiated", 1_000_000, 10, 10_000, 0);
+    params.start_now = true;
+    params.edit_by = 1;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+    let edit = |authority: &Pubkey| {
+        instructions::edit(
+            &stream,
+            authority,
+            Some(&treasury),
+            "renegotiated".to_string(),
+            10,
+            30_000,
+            300,
+        )
+    };
+
+    env.warp(T0 + 100).await;
+    env.fails(edit(&sender.pubkey()), &[&sender], MyError::NotAuthorized)
+        .await;
+    env.ok(edit(&recipient.pubkey()), &[&recipient]).await;
+
+    // The vested 100_000 is settled, the rest streams three times as fast
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 100_000);
+    let account = env.stream(&stream.address).await;
+    assert_eq!(
+        (account.rate_of_stream, account.stop_time),
+        (30_000, T0 + 400)
+    );
+}
+
+#[tokio::test]
+async fn cancel_before_the_cliff_date_pays_the_recipient_nothing() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_000_000).await;
+
+    // 1_000 a second until T0 + 1_000, locked until T0 + 300
+    let mut params = params("locked", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    params.cliff_time = T0 + 300;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+
+    // 200_000 has accrued but none of it is released before the cliff date
+    env.warp(T0 + 200).await;
+    let account = env.stream(&stream.address).await;
+    assert_eq!(account.withdrawable_at(T0 + 200).unwrap(), 200_000);
+    assert_eq!(account.owed_on_cancel_at(T0 + 200).unwrap(), 0);
+    env.ok(
+        instructions::cancel(&stream, &sender.pubkey(), Some(&treasury)),
+        &[&sender],
+    )
+    .await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 0);
+    assert_eq!(env.tokens(&sender.pubkey(), &asset).await, 1_000_000);
+}
+
+#[tokio::test]
+async fn partial_withdrawals_take_the_fee_of_their_amount() {
+    let mut env = Env::new().await;
+    env.set_fee(100).await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_000_000).await;
+
+    let mut params = params("instalments", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+    let withdraw =
+        |amount| instructions::withdraw(&stream, &recipient.pubkey(), Some(&treasury), amount);
+
+    // 100_000 has vested, taken in two parts
+    env.warp(T0 + 100).await;
+    env.ok(withdraw(Some(30_000)), &[&recipient]).await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 29_700);
+    assert_eq!(env.tokens(&treasury, &asset).await, 300);
+
+    env.fails(withdraw(Some(0)), &[&recipient], MyError::AmountIsZero)
+        .await;
+    env.fails(
+        withdraw(Some(70_001)),
+        &[&recipient],
+        MyError::AmountExceedsWithdrawable,
+    )
+    .await;
+    env.ok(withdraw(Some(70_000)), &[&recipient]).await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 99_000);
+    assert_eq!(env.stream(&stream.address).await.withdrawn, 100_000);
+    env.fails(withdraw(None), &[&recipient], MyError::NothingToWithdraw)
+        .await;
+}
+
+#[tokio::test]
+async fn withdraw_to_pays_a_token_account_of_the_recipients_choice() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let exchange = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_000_000).await;
+    let deposit_address = env.token_account(&exchange.pubkey(), &asset).await;
+
+    let mut params = params("offramp", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+    let withdraw_to = |destination: &Pubkey, amount| {
+        instructions::withdraw_to(&stream, destination, Some(&treasury), amount)
+    };
+
+    env.warp(T0 + 100).await;
+    env.ok(withdraw_to(&deposit_address, Some(40_000)), &[&recipient])
+        .await;
+    env.ok(withdraw_to(&deposit_address, None), &[&recipient])
+        .await;
+    assert_eq!(env.tokens(&exchange.pubkey(), &asset).await, 100_000);
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 0);
+
+    // The vault of the stream cannot receive its own payout
+    env.warp(T0 + 200).await;
+    let Asset::Token { mint, .. } = asset else {
+        unreachable!()
+    };
+    let vault = pda::vault_address(&stream.address, &mint, &spl_token::ID);
+    env.fails(
+        withdraw_to(&vault, None),
+        &[&recipient],
+        MyError::InvalidDestination,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn batch_withdrawal_reports_every_stream() {
+    let mut env = Env::new().await;
+    env.set_fee(100).await;
+    let treasury = env.treasury;
+    let recipient = env.user().await;
+    let (first, second) = (env.user().await, env.user().await);
+    let (usdc, eurc) = (
+        env.mint(spl_token::ID, 6, None).await,
+        env.mint(spl_token::ID, 6, None).await,
+    );
+
+    // Two employers paying in different mints, the second one pauses
+    let mut streams = Vec::new();
+    for (sender, asset) in [(&first, usdc), (&second, eurc)] {
+        env.mint_to(&sender.pubkey(), &asset, 100_000).await;
+        env.token_account(&treasury, &asset).await;
+        env.token_account(&recipient.pubkey(), &asset).await;
+        let mut params = params("salary", 100_000, 1, 1_000, 0);
+        params.start_now = true;
+        streams.push(
+            env.create(sender, &recipient.pubkey(), asset, &params)
+                .await,
+        );
+    }
+    env.warp(T0 + 20).await;
+    env.ok(
+        instructions::pause(&streams[1], &second.pubkey(), Some(&treasury)),
+        &[&second],
+    )
+    .await;
+
+    env.warp(T0 + 60).await;
+    let ix = instructions::withdraw_batch(
+        &recipient.pubkey(),
+        &spl_token::ID,
+        Some(&treasury),
+        &streams,
+    );
+    let blockhash = env.ctx.banks_client.get_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        std::slice::from_ref(&ix),
+        Some(&env.ctx.payer.pubkey()),
+        &[&env.ctx.payer, &recipient],
+        blockhash,
+    );
+    let simulation = env.ctx.banks_client.simulate_transaction(tx).await.unwrap();
+    let return_data = simulation
+        .simulation_details
+        .unwrap()
+        .return_data
+        .expect("no return data");
+    let results = accounts::decode_batch_withdrawals(&return_data.data).unwrap();
+    env.ok(ix, &[&recipient]).await;
+
+    // The paused stream paid out when it was paused and is skipped
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        (
+            results[0].stream,
+            results[0].amount,
+            results[0].protocol_fee
+        ),
+        (streams[0].address, 60_000, 600)
+    );
+    assert_eq!(
+        (results[1].stream, results[1].amount),
+        (streams[1].address, 0)
+    );
+    assert_eq!(env.tokens(&recipient.pubkey(), &usdc).await, 59_400);
+    assert_eq!(env.tokens(&recipient.pubkey(), &eurc).await, 19_800);
+}
+
+#[tokio::test]
+async fn native_and_token_streams_share_one_engine() {
+    let mut env = Env::new().await;
+    let treasury = env.treasury;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    let asset = env.mint(spl_token::ID, 6, None).await;
+    env.mint_to(&sender.pubkey(), &asset, 1_000_000).await;
+
+    // The same schedule twice, in lamports and in tokens
+    let mut streams = Vec::new();
+    for (id, asset) in [("in-sol", Asset::Native), ("in-tokens", asset)] {
+        let mut params = params(id, 1_000_000, 10, 10_000, 0);
+        params.start_now = true;
+        params.cliff_amount = 100_000;
+        params.rate = 9_000;
+        streams.push(
+            env.create(&sender, &recipient.pubkey(), asset, &params)
+                .await,
+        );
+    }
+
+    for (timestamp, step) in [(T0 + 95, 0), (T0 + 200, 1), (T0 + 450, 2), (T0 + 600, 0)] {
+        env.warp(timestamp).await;
+        for stream in &streams {
+            let ix = match step {
+                0 => instructions::withdraw(stream, &recipient.pubkey(), Some(&treasury), None),
+                1 => instructions::pause(stream, &sender.pubkey(), Some(&treasury)),
+                _ => instructions::resume(stream, &sender.pubkey()),
+            };
+            let signer = if step == 0 { &recipient } else { &sender };
+            env.ok(ix, &[signer]).await;
+        }
+    }
+
+    let native = env.stream(&streams[0].address).await;
+    let token = env.stream(&streams[1].address).await;
+    assert!(native.is_native() && !token.is_native());
+    assert_eq!(
+        (
+            native.withdrawn,
+            native.remaining_balance,
+            native.start_time,
+            native.stop_time
+        ),
+        (
+            token.withdrawn,
+            token.remaining_balance,
+            token.start_time,
+            token.stop_time
+        )
+    );
+    // The cliff, twenty intervals paid by the pause and fifteen since the resume
+    assert_eq!(native.withdrawn, 100_000 + 20 * 9_000 + 15 * 9_000);
+    assert_eq!(
+        env.tokens(&recipient.pubkey(), &asset).await,
+        token.withdrawn
+    );
+
+    // A token instruction cannot move the lamports of a native stream
+    env.token_account(&streams[0].address, &asset).await;
+    let as_token = StreamRef {
+        asset,
+        ..streams[0].clone()
+    };
+    env.fails(
+        instructions::withdraw(&as_token, &recipient.pubkey(), Some(&treasury), None),
+        &[&recipient],
+        MyError::IncorrectTokenAddress,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn capped_transfer_fees_are_grossed_up_and_reported() {
+    let mut env = Env::new().await;
+    let sender = env.user().await;
+    let recipient = env.user().await;
+    // 5%, at most 300 per transfer
+    let asset = env.mint(spl_token_2022::ID, 6, Some((500, 300))).await;
+    env.mint_to(&sender.pubkey(), &asset, 2_000_000).await;
+
+    let mut params = params("capped", 1_000_000, 1, 1_000, 0);
+    params.start_now = true;
+    params.is_infinite = true;
+    let stream = env
+        .create(&sender, &recipient.pubkey(), asset, &params)
+        .await;
+    env.ok(instructions::reload(&stream, 50_000), &[&sender])
+        .await;
+
+    // Both deposits arrive in full, the sender paid the capped fee on top of each
+    assert_eq!(env.tokens(&stream.address, &asset).await, 1_050_000);
+    assert_eq!(env.withheld(&stream.address, &asset).await, 600);
+    assert_eq!(
+        env.tokens(&sender.pubkey(), &asset).await,
+        2_000_000 - 1_050_000 - 600
+    );
+
+    env.warp(T0 + 100).await;
+    env.ok(
+        instructions::withdraw(&stream, &recipient.pubkey(), Some(&env.treasury), None),
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(env.tokens(&recipient.pubkey(), &asset).await, 99_700);
+    let withdrawn: Vec<_> = events::<Withdrawn>()
+        .into_iter()
+        .filter(|e| e.stream == stream.address)
+        .collect();
+    assert_eq!(
+        (withdrawn[0].amount, withdrawn[0].transfer_fee),
+        (100_000, 300)
+    );
+}